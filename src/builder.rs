@@ -0,0 +1,602 @@
+use crate::cache::{self, Cache};
+use crate::error::Result;
+use crate::executor::{Executor, ProcessExecutor};
+use crate::export::EnvSink;
+use crate::paths::Paths;
+use crate::profile::{self, ActiveProfile, Detected};
+use crate::project;
+use crate::report::Report;
+use crate::resolved_env::ResolvedEnv;
+use crate::runner::{RunPlan, Runner};
+use crate::selection::Selection;
+use crate::toolchains;
+use crate::validate::{self, Issue};
+use crate::workspace;
+use crate::yaml_config::{ActionKind, ActionScope, YamlConfig};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Entry point for applications that embed stop-nagging:
+///
+/// ```no_run
+/// use stop_nagging::StopNagging;
+///
+/// let plan = StopNagging::builder()
+///     .ignore_tools(["yarn"])
+///     .profile("dev")
+///     .build()?;
+/// for tool in &plan.report().tools {
+///     eprintln!("{:?}", tool);
+/// }
+/// let report = plan.apply()?;
+/// # Ok::<(), stop_nagging::Error>(())
+/// ```
+///
+/// Nothing is printed unless the builder is asked to with [`Builder::verbose`]
+/// or an [`EnvSink`] that prints.
+#[derive(Debug, Clone, Copy)]
+pub struct StopNagging;
+
+impl StopNagging {
+    pub fn builder() -> Builder {
+        Builder::default()
+    }
+}
+
+/// Where configuration comes from. Sources are layered in the order they are
+/// added with [`YamlConfig::merge`].
+#[derive(Debug, Clone)]
+pub enum ConfigSource {
+    /// The built-in `tools.yaml`
+    Default,
+    File(PathBuf),
+    Yaml(String),
+    Config(YamlConfig),
+}
+
+impl ConfigSource {
+    fn load(self) -> Result<YamlConfig> {
+        match self {
+            ConfigSource::Default => YamlConfig::from_default(),
+            ConfigSource::File(path) => YamlConfig::from_file(path),
+            ConfigSource::Yaml(yaml) => YamlConfig::from_yaml(&yaml),
+            ConfigSource::Config(config) => Ok(config),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+enum ProfileChoice {
+    #[default]
+    None,
+    Named(String),
+    /// `ci`, `container` or `dev`, depending on where we run
+    Detect,
+}
+
+/// Collects the options for a [`Plan`]. Everything is optional; without a
+/// config source the built-in configuration is used.
+pub struct Builder {
+    sources: Vec<ConfigSource>,
+    ignore_tools: Vec<String>,
+    ecosystems: Vec<String>,
+    skip_actions: Vec<String>,
+    only_actions: Vec<String>,
+    kinds: Option<Vec<ActionKind>>,
+    scope: Option<ActionScope>,
+    profile: ProfileChoice,
+    executor: Arc<dyn Executor>,
+    env_sink: Option<Box<dyn EnvSink>>,
+    force_env: bool,
+    jobs: Option<usize>,
+    timeout: Option<Duration>,
+    project_root: Option<PathBuf>,
+    current_dir: Option<PathBuf>,
+    home: Option<PathBuf>,
+    root: Option<PathBuf>,
+    system: bool,
+    recursive: bool,
+    toolchains: bool,
+    cache: bool,
+    force: bool,
+    dry_run: bool,
+    strict: bool,
+    verbose: bool,
+}
+
+impl Default for Builder {
+    fn default() -> Self {
+        Builder {
+            sources: vec![],
+            ignore_tools: vec![],
+            ecosystems: vec![],
+            skip_actions: vec![],
+            only_actions: vec![],
+            kinds: None,
+            scope: None,
+            profile: ProfileChoice::None,
+            executor: Arc::new(ProcessExecutor),
+            env_sink: None,
+            force_env: false,
+            jobs: None,
+            timeout: None,
+            project_root: None,
+            current_dir: None,
+            home: None,
+            root: None,
+            system: false,
+            recursive: false,
+            toolchains: false,
+            cache: false,
+            force: false,
+            dry_run: false,
+            strict: true,
+            verbose: false,
+        }
+    }
+}
+
+impl Builder {
+    /// Adds a configuration source on top of the previous ones.
+    pub fn config_source(mut self, source: ConfigSource) -> Self {
+        self.sources.push(source);
+        self
+    }
+
+    /// Uses an already loaded configuration.
+    pub fn config(self, config: YamlConfig) -> Self {
+        self.config_source(ConfigSource::Config(config))
+    }
+
+    pub fn config_file(self, path: impl Into<PathBuf>) -> Self {
+        self.config_source(ConfigSource::File(path.into()))
+    }
+
+    /// Tools that are never applied.
+    pub fn ignore_tools<I: IntoIterator<Item = S>, S: Into<String>>(mut self, tools: I) -> Self {
+        self.ignore_tools.extend(tools.into_iter().map(Into::into));
+        self
+    }
+
+    /// Only apply tools of these ecosystems.
+    pub fn ecosystems<I: IntoIterator<Item = S>, S: Into<String>>(mut self, ecosystems: I) -> Self {
+        self.ecosystems
+            .extend(ecosystems.into_iter().map(Into::into));
+        self
+    }
+
+    /// Action IDs such as `yarn.enableGlobalCache` to leave out.
+    pub fn skip_actions<I: IntoIterator<Item = S>, S: Into<String>>(mut self, ids: I) -> Self {
+        self.skip_actions.extend(ids.into_iter().map(Into::into));
+        self
+    }
+
+    /// Only apply these action IDs.
+    pub fn only_actions<I: IntoIterator<Item = S>, S: Into<String>>(mut self, ids: I) -> Self {
+        self.only_actions.extend(ids.into_iter().map(Into::into));
+        self
+    }
+
+    /// Kinds of actions to apply, replacing those of the profile.
+    pub fn kinds(mut self, kinds: impl IntoIterator<Item = ActionKind>) -> Self {
+        self.kinds = Some(kinds.into_iter().collect());
+        self
+    }
+
+    /// Only apply global or only project actions.
+    pub fn scope(mut self, scope: ActionScope) -> Self {
+        self.scope = Some(scope);
+        self
+    }
+
+    /// Uses the named profile; [`Builder::build`] fails if it doesn't exist.
+    pub fn profile(mut self, name: impl Into<String>) -> Self {
+        self.profile = ProfileChoice::Named(name.into());
+        self
+    }
+
+    /// Picks the profile the way the CLI does: `ci` on CI, `container` in a
+    /// container and `dev` otherwise.
+    pub fn detect_profile(mut self) -> Self {
+        self.profile = ProfileChoice::Detect;
+        self
+    }
+
+    /// Runs commands with `executor` instead of starting processes.
+    pub fn executor(mut self, executor: impl Executor + 'static) -> Self {
+        self.executor = Arc::new(executor);
+        self
+    }
+
+    /// Hands the resolved env to `sink` after applying. Without a sink the
+    /// env is only part of the report.
+    pub fn env_sink(mut self, sink: impl EnvSink + 'static) -> Self {
+        self.env_sink = Some(Box::new(sink));
+        self
+    }
+
+    /// Overrides env vars that are already set, regardless of their policy.
+    pub fn force_env(mut self, force_env: bool) -> Self {
+        self.force_env = force_env;
+        self
+    }
+
+    /// Maximum number of tools applied in parallel.
+    pub fn jobs(mut self, jobs: usize) -> Self {
+        self.jobs = Some(jobs);
+        self
+    }
+
+    /// Default timeout for each tool command.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// The project that project actions are applied to. Without one, the
+    /// project is looked for from [`Builder::current_dir`].
+    pub fn project_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.project_root = Some(dir.into());
+        self
+    }
+
+    /// Directory to look for the project from, usually the current one. Tools
+    /// apply in the nearest directory between it and the project root that
+    /// they are detected in. Without it or a project, only global actions
+    /// are applied unless [`Builder::scope`] says otherwise.
+    pub fn current_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.current_dir = Some(dir.into());
+        self
+    }
+
+    /// Home directory for the files tools write, instead of the real one.
+    pub fn home(mut self, home: impl Into<PathBuf>) -> Self {
+        self.home = Some(home.into());
+        self
+    }
+
+    /// Prefix for system-level paths such as `/etc/npmrc`, e.g. an image that
+    /// is being built. The home directory defaults to the real one below it.
+    pub fn root(mut self, root: impl Into<PathBuf>) -> Self {
+        self.root = Some(root.into());
+        self
+    }
+
+    /// Runs the `system:` variant of global commands instead of the per-user
+    /// command; global commands without one are skipped.
+    pub fn system(mut self, system: bool) -> Self {
+        self.system = system;
+        self
+    }
+
+    /// Applies the project actions to each workspace member too.
+    pub fn recursive(mut self, recursive: bool) -> Self {
+        self.recursive = recursive;
+        self
+    }
+
+    /// Applies global commands to installed toolchains, e.g. the Node
+    /// versions of nvm, too.
+    pub fn toolchains(mut self, toolchains: bool) -> Self {
+        self.toolchains = toolchains;
+        self
+    }
+
+    /// Skips tools that were applied before and haven't changed since, and
+    /// remembers the tools applied in the user's cache directory.
+    pub fn cache(mut self, cache: bool) -> Self {
+        self.cache = cache;
+        self
+    }
+
+    /// Applies every tool even if the cache says it is up to date.
+    pub fn force(mut self, force: bool) -> Self {
+        self.force = force;
+        self
+    }
+
+    /// Makes [`Plan::apply`] only report what it would do.
+    pub fn dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// With `false`, errors in the configuration don't fail
+    /// [`Builder::build`]; they are returned with the warnings instead, and
+    /// the tools are applied as far as possible. On by default.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Print progress and env details to stderr.
+    pub fn verbose(mut self, verbose: bool) -> Self {
+        self.verbose = verbose;
+        self
+    }
+
+    /// Loads and validates the configuration, chooses the profile and works
+    /// out what would be applied. No tool command runs yet.
+    pub fn build(mut self) -> Result<Plan> {
+        let Setup {
+            config,
+            warnings,
+            profile,
+            selection,
+            project_root,
+            mut messages,
+        } = self.setup()?;
+
+        let workspace_members = match &project_root {
+            Some(root) if self.recursive => {
+                let members = workspace::members(root);
+                if self.verbose {
+                    eprintln!("Found {} workspace members", members.len());
+                }
+                members
+            }
+            None if self.recursive => {
+                messages.push("Warning: Recursing needs a project root; none was found".into());
+                vec![]
+            }
+            _ => vec![],
+        };
+
+        let real_home = std::env::var_os("HOME")
+            .or_else(|| std::env::var_os("USERPROFILE"))
+            .map(PathBuf::from);
+        let paths = Paths {
+            home: Paths::resolve_home(self.home, self.root.as_deref(), real_home.as_deref()),
+            root: self.root,
+        };
+        if let (Some(home), true) = (&paths.home, self.verbose) {
+            eprintln!("Using home directory {}", home.display());
+        }
+
+        let toolchains = match paths.home.as_ref().or(real_home.as_ref()) {
+            Some(home) if self.toolchains => {
+                let vars = std::env::vars().collect();
+                let found = toolchains::discover(home, &vars);
+                if self.verbose {
+                    for toolchain in &found {
+                        eprintln!("Found toolchain {}", toolchain);
+                    }
+                }
+                found
+            }
+            _ => vec![],
+        };
+
+        let cache_dir = if !self.cache {
+            None
+        } else if self.system {
+            Some(paths.system("/var/cache"))
+        } else {
+            match &paths.home {
+                Some(home) => Some(home.join(".cache")),
+                None => std::env::var_os("XDG_CACHE_HOME")
+                    .filter(|dir| !dir.is_empty())
+                    .map(PathBuf::from)
+                    .or_else(|| real_home.as_ref().map(|home| home.join(".cache"))),
+            }
+        };
+
+        let mut runner = Runner::new(config, self.ignore_tools, self.ecosystems, self.verbose)
+            .with_selection(selection)
+            .with_force_env(self.force_env)
+            .with_project_root(project_root)
+            .with_start_dir(self.current_dir)
+            .with_workspace_members(workspace_members)
+            .with_toolchains(toolchains)
+            .with_paths(paths.clone())
+            .with_system(self.system)
+            .with_force(self.force)
+            .with_executor(self.executor);
+        if let Some(dir) = cache_dir {
+            runner = runner.with_cache(Cache::load(cache::file_in(&dir)));
+        }
+        if let Some(jobs) = self.jobs {
+            runner = runner.with_jobs(jobs);
+        }
+        if let Some(timeout) = self.timeout {
+            runner = runner.with_timeout(timeout);
+        }
+        let planned = runner.plan();
+        // Only fills in the report; the details are printed when applying
+        let runner = runner.with_verbose(false).with_dry_run(true);
+        let mut report = runner.execute(&planned);
+        report.messages.splice(0..0, messages.iter().cloned());
+
+        Ok(Plan {
+            runner: runner.with_verbose(self.verbose).with_dry_run(self.dry_run),
+            planned,
+            report,
+            profile,
+            warnings,
+            messages,
+            home: paths.home,
+            dry_run: self.dry_run,
+            env_sink: self.env_sink,
+        })
+    }
+
+    /// The configuration of the project's own tools, as the shell hook and
+    /// `export` need it: the selected actions of the tools that pass the
+    /// filters, and where to detect them. Doesn't look for tools, so it's
+    /// cheap enough to call on every prompt.
+    pub fn project(mut self) -> Result<ProjectConfig> {
+        let setup = self.setup()?;
+        let mut config = setup.selection.apply(&setup.config);
+        config
+            .ecosystems
+            .retain(|name, _| self.ecosystems.is_empty() || self.ecosystems.contains(name));
+        for eco in config.ecosystems.values_mut() {
+            eco.tools
+                .retain(|tool| !self.ignore_tools.contains(&tool.name));
+        }
+        let dirs = match &setup.project_root {
+            Some(root) => project::search_dirs(self.current_dir.as_deref().unwrap_or(root), root),
+            None => vec![],
+        };
+        Ok(ProjectConfig {
+            config,
+            root: setup.project_root,
+            dirs,
+        })
+    }
+
+    /// What [`Builder::build`] and [`Builder::project`] share: the loaded
+    /// configuration, the profile, the selection and the project root.
+    fn setup(&mut self) -> Result<Setup> {
+        let mut sources = std::mem::take(&mut self.sources).into_iter();
+        let mut config = sources.next().unwrap_or(ConfigSource::Default).load()?;
+        for source in sources {
+            config.merge(source.load()?);
+        }
+        let warnings = if self.strict {
+            validate::check(&config)?
+        } else {
+            validate::validate(&config)
+        };
+
+        let profile = match &self.profile {
+            ProfileChoice::None => None,
+            ProfileChoice::Named(name) => {
+                profile::choose(&config, Some(name), &Detected::default())?
+            }
+            ProfileChoice::Detect => profile::choose(&config, None, &Detected::from_system())?,
+        };
+        let mut selection = Selection {
+            skip_actions: std::mem::take(&mut self.skip_actions),
+            only_actions: std::mem::take(&mut self.only_actions),
+            scope: self.scope,
+            ..Default::default()
+        };
+        if let Some(active) = &profile {
+            if self.verbose {
+                eprintln!("Using profile '{}' ({})", active.name, active.reason);
+            }
+            selection = selection.with_profile(&active.profile);
+        }
+        if let Some(kinds) = self.kinds.take() {
+            selection.kinds = kinds;
+        }
+
+        let mut messages = Vec::new();
+        let project_root = self
+            .project_root
+            .clone()
+            .or_else(|| project::find_root(self.current_dir.as_deref()?));
+        match &project_root {
+            Some(root) if self.verbose => eprintln!("Project root: {}", root.display()),
+            Some(_) => {}
+            None if self.scope == Some(ActionScope::Project) => {
+                messages.push("Warning: No project root found; not running project actions".into());
+            }
+            None => {
+                if self.verbose {
+                    eprintln!("No project root found; skipping project actions");
+                }
+                selection.scope = Some(ActionScope::Global);
+            }
+        }
+        for id in selection.unknown_ids(&config) {
+            messages.push(format!("Warning: Unknown action '{}'", id));
+        }
+
+        Ok(Setup {
+            config,
+            warnings,
+            profile,
+            selection,
+            project_root,
+            messages,
+        })
+    }
+}
+
+struct Setup {
+    config: YamlConfig,
+    warnings: Vec<Issue>,
+    profile: Option<ActiveProfile>,
+    selection: Selection,
+    project_root: Option<PathBuf>,
+    /// Warnings for the report
+    messages: Vec<String>,
+}
+
+/// What [`Builder::project`] returns.
+#[derive(Debug, Clone)]
+pub struct ProjectConfig {
+    /// The selected actions of the tools that pass the filters
+    pub config: YamlConfig,
+    pub root: Option<PathBuf>,
+    /// The directories to detect tools in, nearest first; empty without a root
+    pub dirs: Vec<PathBuf>,
+}
+
+/// What [`Plan::apply`] would do: the tools found, their commands and the
+/// env. Inspect it, then apply it or drop it.
+pub struct Plan {
+    runner: Runner,
+    planned: RunPlan,
+    report: Report,
+    profile: Option<ActiveProfile>,
+    warnings: Vec<Issue>,
+    /// Warnings found while setting up, reported with those of the run
+    messages: Vec<String>,
+    /// The home directory to create before running anything
+    home: Option<PathBuf>,
+    dry_run: bool,
+    env_sink: Option<Box<dyn EnvSink>>,
+}
+
+impl Plan {
+    /// The planned tools, with every command not run yet.
+    pub fn report(&self) -> &Report {
+        &self.report
+    }
+
+    pub fn env(&self) -> &ResolvedEnv {
+        &self.report.env
+    }
+
+    pub fn profile(&self) -> Option<&ActiveProfile> {
+        self.profile.as_ref()
+    }
+
+    /// Validation warnings about the configuration, and its errors if the
+    /// builder wasn't [strict](Builder::strict).
+    pub fn warnings(&self) -> &[Issue] {
+        &self.warnings
+    }
+
+    /// The plan as `--dry-run` prints it.
+    pub fn render(&self) -> String {
+        self.report.render_plan()
+    }
+
+    /// Runs the commands of the planned tools with the planned env, without
+    /// looking for tools again, and hands the env to the sink. Failing
+    /// commands don't fail the call; they are reported in the returned report.
+    /// In a [dry run](Builder::dry_run) nothing is run or handed over.
+    pub fn apply(self) -> Result<Report> {
+        let mut messages = self.messages;
+        if let Some(home) = self.home.as_ref().filter(|_| !self.dry_run) {
+            if let Err(e) = std::fs::create_dir_all(home) {
+                messages.push(format!(
+                    "Warning: Failed to create home directory {}: {}",
+                    home.display(),
+                    e
+                ));
+            }
+        }
+        let mut report = self.runner.execute(&self.planned);
+        report.messages.splice(0..0, messages);
+        if let Some(mut sink) = self.env_sink.filter(|_| !self.dry_run) {
+            sink.apply(&report.env)?;
+        }
+        Ok(report)
+    }
+}
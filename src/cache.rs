@@ -0,0 +1,122 @@
+use crate::error::{Error, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// Fingerprints of the tools applied on earlier runs, so unchanged tools
+/// can be skipped without running any commands.
+#[derive(Debug, Clone, Default)]
+pub struct Cache {
+    path: PathBuf,
+    tools: BTreeMap<String, String>,
+    changed: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CacheFile {
+    tools: BTreeMap<String, String>,
+}
+
+impl Cache {
+    /// Loads the cache at `path`. A missing or unreadable file gives an
+    /// empty cache, so every tool is applied again.
+    pub fn load(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let tools = fs::read_to_string(&path)
+            .ok()
+            .and_then(|contents| serde_json::from_str::<CacheFile>(&contents).ok())
+            .map(|file| file.tools)
+            .unwrap_or_default();
+        Cache {
+            path,
+            tools,
+            changed: false,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Whether `key` was last applied with the same fingerprint.
+    pub fn is_fresh(&self, key: &str, fingerprint: &str) -> bool {
+        self.tools
+            .get(key)
+            .is_some_and(|stored| stored == fingerprint)
+    }
+
+    pub fn record(&mut self, key: &str, fingerprint: &str) {
+        if !self.is_fresh(key, fingerprint) {
+            self.tools.insert(key.to_string(), fingerprint.to_string());
+            self.changed = true;
+        }
+    }
+
+    /// Writes the cache if anything was recorded since it was loaded.
+    pub fn save(&self) -> Result<()> {
+        if !self.changed {
+            return Ok(());
+        }
+        let io_error = |e| Error::io(&self.path, e);
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir).map_err(|e| Error::io(dir, e))?;
+        }
+        let file = CacheFile {
+            tools: self.tools.clone(),
+        };
+        let contents = serde_json::to_string_pretty(&file).map_err(|e| io_error(e.into()))?;
+        fs::write(&self.path, contents).map_err(io_error)
+    }
+}
+
+/// The cache file below a cache directory such as `$XDG_CACHE_HOME`.
+pub fn file_in(cache_dir: &Path) -> PathBuf {
+    cache_dir.join("stop-nagging").join("fingerprints.json")
+}
+
+/// Hashes the description of a tool's actions together with the path, mtime
+/// and size of its executable. An upgrade replaces the executable, so this
+/// stands in for its version without having to run it.
+pub fn fingerprint(actions: &str, executable: Option<&Path>) -> String {
+    let mut hasher = Fnv64::new();
+    hasher.write(actions.as_bytes());
+    if let Some(path) = executable {
+        let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        hasher.write(path.to_string_lossy().as_bytes());
+        if let Ok(meta) = fs::metadata(&path) {
+            let mtime = meta
+                .modified()
+                .ok()
+                .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                .unwrap_or_default();
+            hasher.write(&mtime.as_nanos().to_le_bytes());
+            hasher.write(&meta.len().to_le_bytes());
+        }
+    }
+    format!("{:016x}", hasher.finish())
+}
+
+/// FNV-1a, which is stable across Rust versions unlike `DefaultHasher`.
+struct Fnv64(u64);
+
+impl Fnv64 {
+    fn new() -> Self {
+        Fnv64(0xcbf2_9ce4_8422_2325)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+        // Separate fields so that "ab" + "c" differs from "a" + "bc"
+        self.0 ^= 0xff;
+        self.0 = self.0.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
@@ -1,11 +1,18 @@
-use clap::Parser;
+use crate::export::EnvFile;
+use crate::hook::Shell;
+use crate::yaml_config::{ActionKind, ActionScope};
+use clap::{Parser, Subcommand, ValueEnum};
 use std::path::PathBuf;
+use std::time::Duration;
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
     /// Optional path to a custom YAML configuration file
-    #[arg(short, long)]
+    #[arg(short, long, global = true)]
     pub yaml: Option<PathBuf>,
 
     /// Comma-separated list of tool names to ignore
@@ -16,7 +23,180 @@ pub struct Cli {
     #[arg(long, value_delimiter = ',')]
     pub ecosystems: Option<Vec<String>>,
 
-    /// Enable verbose output
+    /// Comma-separated list of action IDs to skip, e.g. `yarn.enableGlobalCache`
+    #[arg(long, value_delimiter = ',')]
+    pub skip_actions: Option<Vec<String>>,
+
+    /// Comma-separated list of action IDs to apply; all others are skipped
+    #[arg(long, value_delimiter = ',')]
+    pub only_actions: Option<Vec<String>>,
+
+    /// Comma-separated list of action kinds to apply: update_nag, telemetry,
+    /// prompt, behavior (defaults to everything but behavior)
+    #[arg(long, value_delimiter = ',')]
+    pub kinds: Option<Vec<ActionKind>>,
+
+    /// Profile to use, e.g. `ci` or `dev` (picked automatically if not given)
+    #[arg(long)]
+    pub profile: Option<String>,
+
+    /// Which actions to apply: global ones, project ones, or all
+    #[arg(long, value_enum, default_value_t = ScopeArg::All)]
+    pub scope: ScopeArg,
+
+    /// Project root for project-scoped actions (defaults to the enclosing git
+    /// repository or the nearest directory with a project file)
+    #[arg(long, value_name = "DIR")]
+    pub project_dir: Option<PathBuf>,
+
+    /// Also apply project actions in every workspace member (pnpm, yarn and
+    /// npm workspaces, Gradle multi-project builds, Cargo workspaces)
+    #[arg(long)]
+    pub recursive: bool,
+
+    /// Home directory for the files tools write, instead of the real one.
+    /// Sets HOME, USERPROFILE and XDG_* for every command
+    #[arg(long, value_name = "DIR")]
+    pub home: Option<PathBuf>,
+
+    /// Directory that system-level paths such as /etc are placed under. Without
+    /// --home, the home directory is moved under it too
+    #[arg(long, value_name = "DIR")]
+    pub root: Option<PathBuf>,
+
+    /// Apply every tool, even those already applied with the same configuration
+    /// and executable
+    #[arg(long)]
+    pub force: bool,
+
+    /// Don't read or write the fingerprints of applied tools
+    #[arg(long)]
+    pub no_cache: bool,
+
+    /// Apply settings for every user: write env vars to /etc/profile.d and
+    /// /etc/environment.d and run the system-wide variant of tool commands.
+    /// Needs root unless --root is given
+    #[arg(long)]
+    pub system: bool,
+
+    /// Only apply tool commands to the toolchain on PATH, not to every version
+    /// installed by nvm, fnm, pyenv, rbenv, rustup, asdf or mise
+    #[arg(long)]
+    pub no_toolchains: bool,
+
+    /// Show the actions that would be applied, with their IDs, without running any commands
+    #[arg(long, conflicts_with = "print_env")]
+    pub dry_run: bool,
+
+    /// Maximum number of tools to process in parallel (defaults to the number of CPUs)
     #[arg(short, long)]
+    pub jobs: Option<usize>,
+
+    /// Default timeout in seconds for each tool command (defaults to 60)
+    #[arg(long, value_name = "SECONDS", value_parser = parse_seconds)]
+    pub timeout: Option<Duration>,
+
+    /// Override already-set env vars regardless of their policy
+    #[arg(long)]
+    pub force_env: bool,
+
+    /// Print `export` statements for the resolved environment variables
+    #[arg(long)]
+    pub print_env: bool,
+
+    /// Enable verbose output
+    #[arg(short, long, global = true)]
     pub verbose: bool,
 }
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScopeArg {
+    Global,
+    Project,
+    All,
+}
+
+impl ScopeArg {
+    /// The scope to restrict actions to, or `None` for all of them.
+    pub fn scope(self) -> Option<ActionScope> {
+        match self {
+            ScopeArg::Global => Some(ActionScope::Global),
+            ScopeArg::Project => Some(ActionScope::Project),
+            ScopeArg::All => None,
+        }
+    }
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Check the configuration for problems without running anything
+    Validate,
+    /// Write the env vars of the tools detected in the project into a managed
+    /// section of its `.envrc` or `.env`
+    Export {
+        #[arg(value_enum)]
+        format: EnvFile,
+        /// File to update (defaults to the file in the project root)
+        #[arg(long, value_name = "PATH")]
+        output: Option<PathBuf>,
+    },
+    /// Manage wrapper scripts that set a tool's env vars right before running it
+    Shims {
+        #[command(subcommand)]
+        action: ShimsCommand,
+    },
+    /// Print a prompt hook that exports project-specific env vars, such as
+    /// framework telemetry opt-outs, whenever you change directories
+    Hook {
+        #[arg(value_enum)]
+        shell: Shell,
+    },
+    /// Print the env changes for the current directory; run by the hook
+    #[command(hide = true)]
+    HookEnv {
+        #[arg(value_enum)]
+        shell: Shell,
+    },
+    /// Run a command and drop the nag lines it prints, such as update
+    /// banners, as declared by the tool's `nag_patterns`
+    Wrap {
+        /// The command to run, after `--`
+        #[arg(
+            trailing_var_arg = true,
+            allow_hyphen_values = true,
+            required = true,
+            value_name = "COMMAND"
+        )]
+        command: Vec<String>,
+    },
+    /// Install tools into a temporary prefix and check that applying them
+    /// silences the nag their `nag_probe` prints
+    Selftest {
+        /// Comma-separated tools to test (defaults to every tool with a `nag_probe`)
+        #[arg(long, value_delimiter = ',')]
+        tool: Vec<String>,
+        /// Shell command that installs the tool instead of its
+        /// `install_for_testing`. It gets `STOP_NAGGING_PREFIX` and
+        /// `STOP_NAGGING_TOOL` and must put the tool in `$STOP_NAGGING_PREFIX/bin`
+        #[arg(long, value_name = "CMD")]
+        installer: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ShimsCommand {
+    /// Write a shim for every detected tool that has env vars. Put the
+    /// directory at the front of PATH to use them
+    Install { dir: PathBuf },
+    /// List the shims in a directory
+    List { dir: PathBuf },
+    /// Delete the shims in a directory; other files are kept
+    Remove { dir: PathBuf },
+}
+
+fn parse_seconds(value: &str) -> Result<Duration, String> {
+    let seconds: f64 = value
+        .parse()
+        .map_err(|_| format!("invalid number: {}", value))?;
+    Duration::try_from_secs_f64(seconds).map_err(|_| format!("invalid duration: {}", value))
+}
@@ -0,0 +1,102 @@
+use crate::yaml_config::Detect;
+use serde_json::Value;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Whether any condition of `detect` holds for the project at `project_root`.
+/// Without a project root, only executables on `PATH` can match.
+pub fn matches(detect: &Detect, project_root: Option<&Path>) -> bool {
+    let in_project = |check: &dyn Fn(&Path) -> bool| project_root.is_some_and(check);
+
+    detect
+        .files
+        .iter()
+        .any(|file| in_project(&|root| root.join(file).exists()))
+        || detect.json_path.iter().any(|(file, paths)| {
+            in_project(&|root| {
+                read_json(&root.join(file))
+                    .is_some_and(|json| paths.iter().any(|path| has_json_path(&json, path)))
+            })
+        })
+        || detect
+            .executables
+            .iter()
+            .any(|name| find_executable(name, project_root).is_some())
+}
+
+/// Whether `detect` looks at project files, so that the answer depends on
+/// the directory it's checked in.
+pub fn looks_at_files(detect: &Detect) -> bool {
+    !detect.files.is_empty() || !detect.json_path.is_empty()
+}
+
+fn read_json(path: &Path) -> Option<Value> {
+    let contents = fs::read_to_string(path).ok()?;
+    serde_json::from_str(&contents).ok()
+}
+
+/// Follows a dotted path such as `dependencies.next` or `workspaces.0`.
+pub fn has_json_path(json: &Value, path: &str) -> bool {
+    path.split('.')
+        .try_fold(json, |value, key| match value {
+            Value::Object(map) => map.get(key),
+            Value::Array(items) => key.parse::<usize>().ok().and_then(|i| items.get(i)),
+            _ => None,
+        })
+        .is_some()
+}
+
+/// Finds an executable. Names with a path separator are resolved against
+/// the project root, or the current directory without one; bare names are
+/// looked up on `PATH`.
+pub fn find_executable(name: &str, project_root: Option<&Path>) -> Option<PathBuf> {
+    let path = Path::new(name);
+    if path.components().count() > 1 {
+        let candidate = match project_root {
+            Some(root) => root.join(path),
+            None => path.to_path_buf(),
+        };
+        return is_executable(&candidate).then_some(candidate);
+    }
+
+    let dirs = env::var_os("PATH")?;
+    env::split_paths(&dirs).find_map(|dir| find_in_dir(name, &dir))
+}
+
+/// Finds the executable `name` directly inside `dir`.
+pub fn find_in_dir(name: &str, dir: &Path) -> Option<PathBuf> {
+    executable_names(name)
+        .into_iter()
+        .map(|name| dir.join(name))
+        .find(|candidate| is_executable(candidate))
+}
+
+#[cfg(unix)]
+fn executable_names(name: &str) -> Vec<String> {
+    vec![name.to_string()]
+}
+
+/// `npm` is installed as `npm.cmd`, so try every PATHEXT extension too.
+#[cfg(not(unix))]
+fn executable_names(name: &str) -> Vec<String> {
+    let exts = env::var("PATHEXT").unwrap_or_else(|_| ".COM;.EXE;.BAT;.CMD".to_string());
+    std::iter::once(name.to_string())
+        .chain(
+            exts.split(';')
+                .filter(|ext| !ext.is_empty())
+                .map(|ext| format!("{}{}", name, ext)),
+        )
+        .collect()
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    fs::metadata(path).is_ok_and(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
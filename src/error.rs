@@ -0,0 +1,108 @@
+use crate::validate::Issue;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Everything that can go wrong in the library.
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum Error {
+    /// A configuration file isn't valid YAML or doesn't match the schema
+    #[error("{}{}", location(.path.as_deref(), .span), .message)]
+    Parse {
+        /// The file, or `None` for the built-in configuration
+        path: Option<PathBuf>,
+        message: String,
+        span: Option<Span>,
+    },
+    /// Reading or writing a file or stream failed
+    #[error("{}{}", location(.path.as_deref(), &None), .source)]
+    Io {
+        path: Option<PathBuf>,
+        #[source]
+        source: io::Error,
+    },
+    /// A command couldn't be started, e.g. because it isn't installed
+    #[error("Failed to run `{command}`: {source}")]
+    Spawn {
+        command: String,
+        #[source]
+        source: io::Error,
+    },
+    /// A command ran longer than allowed and was killed
+    #[error("`{command}` timed out after {timeout:?}")]
+    Timeout { command: String, timeout: Duration },
+    /// `--profile` names a profile the configuration doesn't have
+    #[error("Unknown profile '{0}'")]
+    UnknownProfile(String),
+    /// Tools that wait for each other in their `after:` fields
+    #[error("Tools form an ordering cycle: {}", .0.join(" -> "))]
+    Cycle(Vec<String>),
+    /// The configuration parsed but has errors; holds every issue found,
+    /// warnings included
+    #[error("Invalid configuration: {}", errors(.issues))]
+    Invalid { issues: Vec<Issue> },
+}
+
+/// Where in a configuration file a parse error is, counted from 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Error {
+    pub(crate) fn io(path: impl Into<PathBuf>, source: io::Error) -> Self {
+        Error::Io {
+            path: Some(path.into()),
+            source,
+        }
+    }
+
+    pub(crate) fn parse(path: Option<&Path>, error: serde_yaml::Error) -> Self {
+        let span = error.location().map(|location| Span {
+            line: location.line(),
+            column: location.column(),
+        });
+        let mut message = error.to_string();
+        // serde_yaml ends the message with the location, which Display
+        // already puts in front
+        if let Some(span) = span {
+            let suffix = format!(" at line {} column {}", span.line, span.column);
+            if let Some(stripped) = message.strip_suffix(&suffix) {
+                message.truncate(stripped.len());
+            }
+        }
+        Error::Parse {
+            path: path.map(Path::to_path_buf),
+            span,
+            message,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(source: io::Error) -> Self {
+        Error::Io { path: None, source }
+    }
+}
+
+fn location(path: Option<&Path>, span: &Option<Span>) -> String {
+    match (path, span) {
+        (Some(path), Some(span)) => format!("{}:{}:{}: ", path.display(), span.line, span.column),
+        (Some(path), None) => format!("{}: ", path.display()),
+        (None, Some(span)) => format!("{}:{}: ", span.line, span.column),
+        (None, None) => String::new(),
+    }
+}
+
+fn errors(issues: &[Issue]) -> String {
+    let errors: Vec<String> = issues
+        .iter()
+        .filter(|issue| issue.severity == crate::validate::Severity::Error)
+        .map(|issue| issue.message.clone())
+        .collect();
+    errors.join("; ")
+}
@@ -0,0 +1,404 @@
+use crate::detect;
+use crate::error::{Error, Result};
+use crate::report::CommandOutcome;
+use crate::resolved_env::ResolvedEnv;
+use crate::yaml_config::{default_non_interactive_env, CommandLine};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Output, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Default time a single command may take before it is killed.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(60);
+
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// How a single command is run.
+#[derive(Debug, Clone)]
+pub struct ExecOptions {
+    pub timeout: Duration,
+    /// Run on the terminal, in the foreground, without the non-interactive
+    /// env vars
+    pub interactive: bool,
+    /// Env vars that keep tools from prompting, applied to the child only
+    pub non_interactive_env: Vec<(String, String)>,
+    /// Working directory of the command; the current one if not set
+    pub current_dir: Option<PathBuf>,
+    /// Directory put in front of `PATH`, e.g. a toolchain's `bin`
+    pub path_prefix: Option<PathBuf>,
+    /// Env vars that take precedence over everything else, e.g. `HOME` for `--home`
+    pub extra_env: Vec<(String, String)>,
+}
+
+impl ExecOptions {
+    pub fn new(timeout: Duration) -> Self {
+        ExecOptions {
+            timeout,
+            interactive: false,
+            non_interactive_env: default_non_interactive_env().into_iter().collect(),
+            current_dir: None,
+            path_prefix: None,
+            extra_env: vec![],
+        }
+    }
+}
+
+/// Runs the commands of tools. [`ProcessExecutor`] starts real processes;
+/// applications embedding stop-nagging can record or fake the commands.
+pub trait Executor: Send + Sync {
+    fn run(&self, cmd: &CommandLine, env: &ResolvedEnv, options: &ExecOptions) -> CommandOutcome;
+}
+
+/// Runs commands as child processes with [`run_command_with`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ProcessExecutor;
+
+impl Executor for ProcessExecutor {
+    fn run(&self, cmd: &CommandLine, env: &ResolvedEnv, options: &ExecOptions) -> CommandOutcome {
+        run_command_with(cmd, env, options)
+    }
+}
+
+pub fn check_command(cmd: &str, timeout: Duration) -> bool {
+    let mut command = shell_command(cmd);
+    command.stdin(Stdio::null());
+    matches!(
+        output_with_timeout(command, timeout),
+        Ok(Completion::Exited(output)) if output.status.success()
+    )
+}
+
+/// Runs the shell string `cmd` non-interactively with the default options.
+pub fn run_command(cmd: &str, env: &ResolvedEnv, timeout: Duration) -> CommandOutcome {
+    run_command_with(
+        &CommandLine::Shell(cmd.to_string()),
+        env,
+        &ExecOptions::new(timeout),
+    )
+}
+
+/// Runs `cmd` with the resolved env applied to the child only. The command's
+/// whole process group is killed if it runs longer than the timeout.
+///
+/// Shell strings go through `sh -c`; argv lists are executed directly.
+/// Unless the command is interactive, stdin is connected to `/dev/null` and
+/// output that ends in a question is reported as needing interaction.
+/// Interactive commands use the terminal directly and stay in its foreground
+/// process group, so they can read the answer to their questions.
+pub fn run_command_with(
+    cmd: &CommandLine,
+    env: &ResolvedEnv,
+    options: &ExecOptions,
+) -> CommandOutcome {
+    let Some(command) = build_command(cmd, env, options) else {
+        let source = std::io::Error::new(std::io::ErrorKind::InvalidInput, "empty argv command");
+        let error = Error::Spawn {
+            command: String::new(),
+            source,
+        };
+        return CommandOutcome::SpawnFailed(Arc::new(error));
+    };
+    if options.interactive {
+        return run_on_terminal(command, options.timeout);
+    }
+
+    let completion = match output_with_timeout(command, options.timeout) {
+        Ok(completion) => completion,
+        Err(e) => return CommandOutcome::SpawnFailed(Arc::new(e)),
+    };
+
+    // A command that succeeded didn't wait for an answer, whatever it printed
+    let prompt = match &completion {
+        Completion::Exited(output) if output.status.success() => None,
+        Completion::Exited(output) => detect_prompt(&output.stdout, &output.stderr, false),
+        // A command stuck until the timeout most likely waits for an answer
+        Completion::TimedOut { stdout, stderr } => detect_prompt(stdout, stderr, true),
+    };
+    if let Some(prompt) = prompt {
+        return CommandOutcome::NeedsInteraction(prompt);
+    }
+
+    match completion {
+        Completion::Exited(output) if output.status.success() => CommandOutcome::Success,
+        Completion::Exited(output) => CommandOutcome::Failed {
+            status: output.status.code(),
+            stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+        },
+        Completion::TimedOut { .. } => CommandOutcome::TimedOut(options.timeout),
+    }
+}
+
+/// Builds the child process for `cmd` with the env and options applied, the
+/// way [`run_command_with`] runs it. `None` for an empty argv.
+pub fn build_command(
+    cmd: &CommandLine,
+    env: &ResolvedEnv,
+    options: &ExecOptions,
+) -> Option<Command> {
+    let mut command = match cmd {
+        CommandLine::Shell(line) => shell_command(line),
+        CommandLine::Argv(argv) => argv_command(argv, options.path_prefix.as_deref())?,
+    };
+    if !options.interactive {
+        command.stdin(Stdio::null());
+        command.envs(options.non_interactive_env.iter().map(|(k, v)| (k, v)));
+    }
+    command.envs(env.vars_to_set());
+    command.envs(options.extra_env.iter().map(|(k, v)| (k, v)));
+    if let Some(dir) = &options.path_prefix {
+        let current = env
+            .get("PATH")
+            .map(|entry| entry.effective.clone().into())
+            .or_else(|| std::env::var_os("PATH"))
+            .unwrap_or_default();
+        let dirs = std::iter::once(dir.clone()).chain(std::env::split_paths(&current));
+        if let Ok(path) = std::env::join_paths(dirs) {
+            command.env("PATH", path);
+        }
+    }
+    if let Some(dir) = &options.current_dir {
+        command.current_dir(dir);
+    }
+    Some(command)
+}
+
+/// Returns the last line of output if it looks like the command asked a
+/// question. Only meant for commands that failed or timed out.
+///
+/// Explicit answer hints such as `[y/N]` always count. A trailing `?` or `:`
+/// only counts when the command was still waiting when it timed out; a
+/// command that exits with `error: could not read config:` just failed.
+pub fn detect_prompt(stdout: &[u8], stderr: &[u8], timed_out: bool) -> Option<String> {
+    let stdout = String::from_utf8_lossy(stdout);
+    let stderr = String::from_utf8_lossy(stderr);
+
+    [stdout, stderr].iter().find_map(|output| {
+        let line = output.lines().rev().find(|l| !l.trim().is_empty())?.trim();
+        let lower = line.to_ascii_lowercase();
+        let hinted = [
+            "[y/n]",
+            "(y/n)",
+            "[yes/no]",
+            "(yes/no)",
+            "password:",
+            "passphrase",
+        ]
+        .iter()
+        .any(|hint| lower.contains(hint));
+        let asks = line.ends_with('?') || line.ends_with(':');
+        (hinted || (asks && timed_out)).then(|| line.to_string())
+    })
+}
+
+fn shell_command(cmd: &str) -> Command {
+    let mut command = Command::new("sh");
+    command.arg("-c").arg(cmd);
+    command
+}
+
+fn argv_command(argv: &[String], path_prefix: Option<&Path>) -> Option<Command> {
+    let (program, args) = argv.split_first()?;
+    // Prefer the toolchain's own binary over whatever is first on PATH
+    let resolved = path_prefix
+        .and_then(|dir| detect::find_in_dir(program, dir))
+        .unwrap_or_else(|| resolve_program(program));
+    let mut command = Command::new(resolved);
+    command.args(args);
+    Some(command)
+}
+
+#[cfg(unix)]
+fn resolve_program(program: &str) -> PathBuf {
+    program.into()
+}
+
+/// Finds `program` on PATH, trying each PATHEXT extension so that `npm`
+/// resolves to `npm.cmd`. Falls back to the bare name.
+#[cfg(not(unix))]
+fn resolve_program(program: &str) -> PathBuf {
+    if Path::new(program).extension().is_some() {
+        return program.into();
+    }
+    detect::find_executable(program, None).unwrap_or_else(|| program.into())
+}
+
+/// Runs `command` with the terminal as its stdin, stdout and stderr. Unlike
+/// [`output_with_timeout`] it stays in our process group: a child in a
+/// background group is stopped with `SIGTTIN` as soon as it reads the terminal.
+/// On timeout only the command itself is killed.
+fn run_on_terminal(mut command: Command, timeout: Duration) -> CommandOutcome {
+    command
+        .stdin(Stdio::inherit())
+        .stdout(Stdio::inherit())
+        .stderr(Stdio::inherit());
+    let mut child = match command.spawn() {
+        Ok(child) => child,
+        Err(source) => {
+            let command = describe(&command);
+            return CommandOutcome::SpawnFailed(Arc::new(Error::Spawn { command, source }));
+        }
+    };
+    let start = Instant::now();
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) if status.success() => return CommandOutcome::Success,
+            Ok(Some(status)) => {
+                return CommandOutcome::Failed {
+                    status: status.code(),
+                    stderr: String::new(),
+                }
+            }
+            Ok(None) => {}
+            Err(e) => return CommandOutcome::SpawnFailed(Arc::new(e.into())),
+        }
+        if start.elapsed() >= timeout {
+            let _ = child.kill();
+            let _ = child.wait();
+            return CommandOutcome::TimedOut(timeout);
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// How a command run with a timeout ended.
+#[derive(Debug)]
+pub enum Completion {
+    Exited(Output),
+    /// The command was killed; this is whatever it printed until then
+    TimedOut {
+        stdout: Vec<u8>,
+        stderr: Vec<u8>,
+    },
+}
+
+/// Like `Command::output`, but fails with [`Error::Timeout`] and kills the
+/// command if it runs longer than `timeout`.
+pub fn output(command: Command, timeout: Duration) -> Result<Output> {
+    let description = describe(&command);
+    match output_with_timeout(command, timeout)? {
+        Completion::Exited(output) => Ok(output),
+        Completion::TimedOut { .. } => Err(Error::Timeout {
+            command: description,
+            timeout,
+        }),
+    }
+}
+
+/// Like `Command::output`, but kills the command after `timeout` and keeps
+/// what it printed until then. Processes it left running in the background
+/// count towards the timeout as long as they hold its stdout or stderr.
+pub fn output_with_timeout(mut command: Command, timeout: Duration) -> Result<Completion> {
+    command.stdout(Stdio::piped()).stderr(Stdio::piped());
+
+    // Give the command its own process group so everything it spawns can be killed
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        command.process_group(0);
+    }
+
+    let mut child = command.spawn().map_err(|source| Error::Spawn {
+        command: describe(&command),
+        source,
+    })?;
+    let stdout = child.stdout.take().map(read_in_background);
+    let stderr = child.stderr.take().map(read_in_background);
+
+    let start = Instant::now();
+    let mut status = None;
+    // A background grandchild can keep the pipes open after the command
+    // exited, so wait for the readers within the same deadline
+    while status.is_none() || !stdout.iter().chain(&stderr).all(Reader::is_finished) {
+        if status.is_none() {
+            status = child.try_wait()?;
+        }
+        if start.elapsed() >= timeout {
+            kill_process_group(&mut child);
+            let _ = child.wait();
+            // Don't wait for the readers; a detached grandchild may still hold the pipes
+            return Ok(Completion::TimedOut {
+                stdout: stdout.map(|r| r.collected()).unwrap_or_default(),
+                stderr: stderr.map(|r| r.collected()).unwrap_or_default(),
+            });
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+    let status = status.expect("the loop only ends once the command exited");
+
+    Ok(Completion::Exited(Output {
+        status,
+        stdout: stdout.map(|r| r.finish()).unwrap_or_default(),
+        stderr: stderr.map(|r| r.finish()).unwrap_or_default(),
+    }))
+}
+
+/// The program and its arguments, for error messages.
+fn describe(command: &Command) -> String {
+    std::iter::once(command.get_program())
+        .chain(command.get_args())
+        .map(|arg| arg.to_string_lossy())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+struct Reader {
+    buf: Arc<Mutex<Vec<u8>>>,
+    handle: thread::JoinHandle<()>,
+}
+
+impl Reader {
+    /// Waits for the pipe to close and returns everything read from it.
+    fn finish(self) -> Vec<u8> {
+        let Reader { buf, handle } = self;
+        let _ = handle.join();
+        let collected = buf.lock().map(|buf| buf.clone()).unwrap_or_default();
+        collected
+    }
+
+    /// Whether the pipe was closed and everything was read.
+    fn is_finished(&self) -> bool {
+        self.handle.is_finished()
+    }
+
+    /// Returns what has been read so far without waiting.
+    fn collected(&self) -> Vec<u8> {
+        self.buf.lock().map(|buf| buf.clone()).unwrap_or_default()
+    }
+}
+
+fn read_in_background<R: Read + Send + 'static>(mut pipe: R) -> Reader {
+    let buf = Arc::new(Mutex::new(Vec::new()));
+    let shared = Arc::clone(&buf);
+    let handle = thread::spawn(move || {
+        let mut chunk = [0u8; 4096];
+        while let Ok(n) = pipe.read(&mut chunk) {
+            if n == 0 {
+                break;
+            }
+            if let Ok(mut buf) = shared.lock() {
+                buf.extend_from_slice(&chunk[..n]);
+            }
+        }
+    });
+    Reader { buf, handle }
+}
+
+#[cfg(unix)]
+fn kill_process_group(child: &mut Child) {
+    // The child is the leader of its own group, so its pid is the group id
+    let pgid = child.id() as libc::pid_t;
+    unsafe {
+        libc::kill(-pgid, libc::SIGKILL);
+    }
+    let _ = child.kill();
+}
+
+#[cfg(not(unix))]
+fn kill_process_group(child: &mut Child) {
+    let _ = Command::new("taskkill")
+        .args(["/T", "/F", "/PID", &child.id().to_string()])
+        .output();
+    let _ = child.kill();
+}
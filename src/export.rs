@@ -0,0 +1,234 @@
+use crate::error::{Error, Result};
+use crate::paths::Paths;
+use crate::resolved_env::{EnvEntry, EnvStatus, ResolvedEnv};
+use crate::yaml_config::EnvMode;
+use clap::ValueEnum;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+
+/// Login shell script that `--system` writes the env to.
+pub const PROFILE_SCRIPT: &str = "/etc/profile.d/stop-nagging.sh";
+/// systemd `environment.d` file that `--system` writes the env to.
+pub const ENVIRONMENT_D_FILE: &str = "/etc/environment.d/50-stop-nagging.conf";
+
+/// Renders the variables stop-nagging would set as POSIX `export` lines,
+/// suitable for `eval "$(stop-nagging --print-env)"`.
+///
+/// `append_token` entries are rendered as an expression over the variable's
+/// current value, so whatever the shell already has is kept.
+pub fn render_posix(env: &ResolvedEnv) -> String {
+    env.entries_to_set()
+        .map(|(key, entry)| posix_line(key, entry))
+        .collect()
+}
+
+/// Receives the env once the tools are applied, so that an application
+/// embedding stop-nagging decides where it goes.
+///
+/// There is deliberately no sink that calls `std::env::set_var`: changing the
+/// process env races with other threads reading it. Pass
+/// [`ResolvedEnv::vars_to_set`] to `Command::envs` for the children instead.
+pub trait EnvSink {
+    fn apply(&mut self, env: &ResolvedEnv) -> Result<()>;
+}
+
+/// Writes the env as `export` lines, like `--print-env`.
+#[derive(Debug)]
+pub struct PosixScript<W: Write>(pub W);
+
+impl<W: Write> EnvSink for PosixScript<W> {
+    fn apply(&mut self, env: &ResolvedEnv) -> Result<()> {
+        self.0.write_all(render_posix(env).as_bytes())?;
+        Ok(())
+    }
+}
+
+/// Renders the env entries claimed by `tool` as `export` lines, for scripts
+/// that set them right before running the tool.
+pub fn render_tool_posix(env: &ResolvedEnv, tool: &str) -> String {
+    persisted(env)
+        .filter(|(_, entry)| entry.tool == tool)
+        .map(|(key, entry)| posix_line(key, entry))
+        .collect()
+}
+
+/// Renders the env as a script for `/etc/profile.d`. Unlike [`render_posix`],
+/// variables that already have the desired value here are included, since
+/// other users' shells don't have them.
+pub fn render_profile_script(env: &ResolvedEnv) -> String {
+    let mut out = String::from("# Written by stop-nagging --system\n");
+    for (key, entry) in persisted(env) {
+        out.push_str(&posix_line(key, entry));
+    }
+    out
+}
+
+/// Renders the env in the `KEY=VALUE` format of systemd's `environment.d`.
+pub fn render_environment_d(env: &ResolvedEnv) -> String {
+    let mut out = String::from("# Written by stop-nagging --system\n");
+    for (key, entry) in persisted(env) {
+        let line = match entry.mode {
+            EnvMode::Set => format!("{}={}\n", key, env_d_quote(&entry.value)),
+            EnvMode::AppendToken => format!(
+                "{key}=\"${{{key}:+${{{key}}}{sep}}}{token}\"\n",
+                key = key,
+                sep = env_d_escape(entry.separator.as_str()),
+                token = env_d_escape(&entry.value)
+            ),
+        };
+        out.push_str(&line);
+    }
+    out
+}
+
+/// Writes the profile script and the `environment.d` file under the root of
+/// `paths`, creating their directories. Returns the files written.
+pub fn write_system_env(env: &ResolvedEnv, paths: &Paths) -> Result<Vec<PathBuf>> {
+    let files = [
+        (PROFILE_SCRIPT, render_profile_script(env)),
+        (ENVIRONMENT_D_FILE, render_environment_d(env)),
+    ];
+    let mut written = Vec::new();
+    for (path, contents) in files {
+        let path = paths.system(path);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| Error::io(dir, e))?;
+        }
+        fs::write(&path, contents).map_err(|e| Error::io(&path, e))?;
+        written.push(path);
+    }
+    Ok(written)
+}
+
+/// Entries stop-nagging wants for everyone; values kept by policy are left out.
+fn persisted(env: &ResolvedEnv) -> impl Iterator<Item = (&String, &EnvEntry)> {
+    env.iter()
+        .filter(|(_, entry)| entry.status != EnvStatus::Kept)
+}
+
+fn posix_line(key: &str, entry: &EnvEntry) -> String {
+    match entry.mode {
+        EnvMode::Set => format!("export {}={}\n", key, shell_quote(&entry.value)),
+        EnvMode::AppendToken => format!(
+            "export {key}=\"${{{key}:+${{{key}}}{sep}}}\"{token}\n",
+            key = key,
+            sep = entry.separator.as_str(),
+            token = shell_quote(&entry.value)
+        ),
+    }
+}
+
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+fn env_d_quote(value: &str) -> String {
+    format!("\"{}\"", env_d_escape(value))
+}
+
+fn env_d_escape(value: &str) -> String {
+    value
+        .replace('\\', r"\\")
+        .replace('"', "\\\"")
+        .replace('$', r"\$")
+}
+
+/// First line of the section stop-nagging manages in `.envrc` and `.env` files.
+pub const SECTION_START: &str = "# >>> stop-nagging >>>";
+/// Last line of the managed section.
+pub const SECTION_END: &str = "# <<< stop-nagging <<<";
+
+/// Project env files that `export` can write.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EnvFile {
+    /// direnv's `.envrc`, a shell script
+    Envrc,
+    /// A `.env` file for dotenv loaders
+    Dotenv,
+}
+
+impl EnvFile {
+    pub fn file_name(&self) -> &'static str {
+        match self {
+            EnvFile::Envrc => ".envrc",
+            EnvFile::Dotenv => ".env",
+        }
+    }
+}
+
+/// Puts the env into the managed section of `existing`, replacing the section
+/// if there is one and appending it otherwise. Keys assigned outside the
+/// section are left to the user. `.env` files can't append to a value, so
+/// `append_token` entries are only written to `.envrc`.
+pub fn merge_env_file(existing: &str, env: &ResolvedEnv, format: EnvFile) -> String {
+    let section = existing.find(SECTION_START).map(|start| {
+        let end = existing[start..]
+            .find(SECTION_END)
+            .map_or(existing.len(), |end| start + end + SECTION_END.len());
+        (start, end)
+    });
+    let (before, after) = match section {
+        Some((start, end)) => {
+            let after = &existing[end..];
+            (
+                &existing[..start],
+                after.strip_prefix('\n').unwrap_or(after),
+            )
+        }
+        None => (existing, ""),
+    };
+    let user_keys: Vec<&str> = before
+        .lines()
+        .chain(after.lines())
+        .filter_map(assigned_key)
+        .collect();
+
+    let mut out = before.to_string();
+    if !out.is_empty() && !out.ends_with('\n') {
+        out.push('\n');
+    }
+    // Keep a new section apart from what the user wrote
+    if section.is_none() && !out.is_empty() {
+        out.push('\n');
+    }
+    out.push_str(SECTION_START);
+    out.push('\n');
+    for (key, entry) in persisted(env).filter(|(key, _)| !user_keys.contains(&key.as_str())) {
+        match (format, entry.mode) {
+            (EnvFile::Envrc, _) => out.push_str(&posix_line(key, entry)),
+            (EnvFile::Dotenv, EnvMode::Set) => {
+                out.push_str(&format!("{}={}\n", key, dotenv_quote(&entry.value)))
+            }
+            (EnvFile::Dotenv, EnvMode::AppendToken) => {}
+        }
+    }
+    out.push_str(SECTION_END);
+    out.push('\n');
+    out.push_str(after);
+    out
+}
+
+/// The key of a `KEY=value` or `export KEY=value` line.
+fn assigned_key(line: &str) -> Option<&str> {
+    let line = line.trim_start();
+    let line = line.strip_prefix("export ").unwrap_or(line).trim_start();
+    let (key, _) = line.split_once('=')?;
+    let valid = !key.is_empty()
+        && !key.starts_with(|c: char| c.is_ascii_digit())
+        && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    valid.then_some(key)
+}
+
+fn dotenv_quote(value: &str) -> String {
+    let plain = value
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || "-_./:,@+=%".contains(c));
+    if plain {
+        value.to_string()
+    } else if !value.contains('\'') {
+        format!("'{}'", value)
+    } else {
+        format!("\"{}\"", value.replace('\\', r"\\").replace('"', "\\\""))
+    }
+}
@@ -0,0 +1,241 @@
+use crate::cache;
+use crate::detect;
+use crate::resolved_env::ResolvedEnv;
+use crate::yaml_config::{Detect, Ecosystem, EnvMode, Tool, YamlConfig};
+use clap::ValueEnum;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// The project vars exported by the last hook run and the values they had
+/// before, as a JSON object; `null` if a var wasn't set
+pub const VARS_VAR: &str = "STOP_NAGGING_PROJECT_VARS";
+/// Stamp of the project state the exported vars were computed for
+pub const STAMP_VAR: &str = "STOP_NAGGING_PROJECT_STAMP";
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+/// The prompt hook for `shell`. It runs `hook-env` before every prompt, so
+/// files created or edited in the current directory are picked up too;
+/// `hook-env` compares the [`stamp`] and prints nothing when it's unchanged.
+pub fn render_hook(shell: Shell, exe: &str) -> String {
+    match shell {
+        Shell::Bash => format!(
+            r#"_stop_nagging_hook() {{
+  local status=$?
+  eval "$({exe} hook-env bash)"
+  return $status
+}}
+if [[ ";${{PROMPT_COMMAND[*]:-}};" != *";_stop_nagging_hook;"* ]]; then
+  PROMPT_COMMAND="_stop_nagging_hook${{PROMPT_COMMAND:+;$PROMPT_COMMAND}}"
+fi
+"#,
+            exe = sh_quote(exe)
+        ),
+        Shell::Zsh => format!(
+            r#"_stop_nagging_hook() {{
+  eval "$({exe} hook-env zsh)"
+}}
+autoload -Uz add-zsh-hook
+add-zsh-hook precmd _stop_nagging_hook
+_stop_nagging_hook
+"#,
+            exe = sh_quote(exe)
+        ),
+        Shell::Fish => format!(
+            r#"function __stop_nagging_hook --on-event fish_prompt
+    {exe} hook-env fish | source
+end
+__stop_nagging_hook
+"#,
+            exe = fish_quote(exe)
+        ),
+    }
+}
+
+/// Whether the tool's env only applies inside matching projects: it or its
+/// ecosystem has a `detect:` block that looks at files.
+fn is_project_tool(ecosystem: &Ecosystem, tool: &Tool) -> bool {
+    [ecosystem.detect.as_ref(), tool.detect.as_ref()]
+        .into_iter()
+        .flatten()
+        .any(detect::looks_at_files)
+}
+
+/// Identifies the state that decides the project env: the directories
+/// searched, the modification times of the files the `detect:` blocks look
+/// at in each and where the executables of the project tools are found, so
+/// that installing one, e.g. with `npm install`, counts as a change.
+pub fn stamp(config: &YamlConfig, dirs: &[PathBuf]) -> String {
+    let mut files: Vec<&String> = config
+        .ecosystems
+        .values()
+        .flat_map(|eco| {
+            eco.detect
+                .iter()
+                .chain(eco.tools.iter().flat_map(|t| &t.detect))
+        })
+        .flat_map(|detect| detect.files.iter().chain(detect.json_path.keys()))
+        .collect();
+    files.sort();
+    files.dedup();
+    let mut executables: Vec<&String> = config
+        .ecosystems
+        .values()
+        .flat_map(|eco| {
+            eco.tools
+                .iter()
+                .filter(move |tool| !tool.skip && is_project_tool(eco, tool))
+        })
+        .map(|tool| &tool.executable)
+        .collect();
+    executables.sort();
+    executables.dedup();
+
+    let mut state = String::new();
+    for dir in dirs {
+        writeln!(state, "dir {}", dir.display()).unwrap();
+        for file in &files {
+            let mtime = fs::metadata(dir.join(file))
+                .and_then(|meta| meta.modified())
+                .ok()
+                .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                .map(|time| time.as_nanos());
+            writeln!(state, "{} {:?}", file, mtime).unwrap();
+        }
+        for executable in &executables {
+            let found = detect::find_executable(executable, Some(dir));
+            writeln!(state, "executable {} {:?}", executable, found).unwrap();
+        }
+    }
+    cache::fingerprint(&state, None)
+}
+
+/// The tools whose env only applies inside projects and that are detected
+/// in one of `dirs`, with their executable installed there. `dirs` are the
+/// directories from [`project::search_dirs`](crate::project::search_dirs).
+pub fn project_tools<'a>(config: &'a YamlConfig, dirs: &[PathBuf]) -> Vec<&'a Tool> {
+    let matches =
+        |detect: Option<&Detect>, dir: &Path| detect.is_none_or(|d| detect::matches(d, Some(dir)));
+    config
+        .ecosystems
+        .values()
+        .flat_map(|eco| {
+            eco.tools
+                .iter()
+                .filter(move |tool| !tool.skip && is_project_tool(eco, tool))
+                .filter(move |tool| {
+                    dirs.iter().any(|dir| {
+                        matches(eco.detect.as_ref(), dir)
+                            && matches(tool.detect.as_ref(), dir)
+                            && detect::find_executable(&tool.executable, Some(dir)).is_some()
+                    })
+                })
+        })
+        .collect()
+}
+
+/// The project env for `dirs`, resolved against `base`. Only plain `set`
+/// entries are included, since appended tokens can't be undone on leaving.
+pub fn project_env(
+    config: &YamlConfig,
+    dirs: &[PathBuf],
+    base: &HashMap<String, String>,
+) -> Vec<(String, String)> {
+    let env = ResolvedEnv::resolve(project_tools(config, dirs), base, false);
+    env.entries_to_set()
+        .filter(|(_, entry)| entry.mode == EnvMode::Set)
+        .map(|(key, entry)| (key.clone(), entry.value.clone()))
+        .collect()
+}
+
+/// Shell code that restores the `previous` vars that are no longer wanted,
+/// exports the `wanted` ones and remembers both for the next run. `base` is
+/// the env without the hook's own exports, see [`user_env`]; the values in it
+/// are what leaving the project restores.
+pub fn render_update(
+    shell: Shell,
+    previous: &BTreeMap<String, Option<String>>,
+    wanted: &[(String, String)],
+    base: &HashMap<String, String>,
+    stamp: &str,
+) -> String {
+    let mut out = String::new();
+    for (key, saved) in previous {
+        if !wanted.iter().any(|(k, _)| k == key) {
+            match saved {
+                Some(value) => out.push_str(&export_line(shell, key, value)),
+                None => out.push_str(&unset_line(shell, key)),
+            }
+        }
+    }
+    for (key, value) in wanted {
+        out.push_str(&export_line(shell, key, value));
+    }
+    let saved: BTreeMap<&str, Option<&String>> = wanted
+        .iter()
+        .map(|(key, _)| (key.as_str(), base.get(key)))
+        .collect();
+    if saved.is_empty() {
+        out.push_str(&unset_line(shell, VARS_VAR));
+    } else {
+        let json = serde_json::to_string(&saved).expect("a map of strings serializes");
+        out.push_str(&export_line(shell, VARS_VAR, &json));
+    }
+    out.push_str(&export_line(shell, STAMP_VAR, stamp));
+    out
+}
+
+/// Parses the value of [`VARS_VAR`]; anything unreadable counts as empty.
+pub fn previous_vars(value: Option<&str>) -> BTreeMap<String, Option<String>> {
+    value
+        .and_then(|value| serde_json::from_str(value).ok())
+        .unwrap_or_default()
+}
+
+/// The env as it was before the hook exported anything: `current` with the
+/// `previous` vars put back to their saved values.
+pub fn user_env(
+    current: impl IntoIterator<Item = (String, String)>,
+    previous: &BTreeMap<String, Option<String>>,
+) -> HashMap<String, String> {
+    let mut env: HashMap<String, String> = current
+        .into_iter()
+        .filter(|(key, _)| !previous.contains_key(key))
+        .collect();
+    for (key, saved) in previous {
+        if let Some(value) = saved {
+            env.insert(key.clone(), value.clone());
+        }
+    }
+    env
+}
+
+fn export_line(shell: Shell, key: &str, value: &str) -> String {
+    match shell {
+        Shell::Bash | Shell::Zsh => format!("export {}={}\n", key, sh_quote(value)),
+        Shell::Fish => format!("set -gx {} {}\n", key, fish_quote(value)),
+    }
+}
+
+fn unset_line(shell: Shell, key: &str) -> String {
+    match shell {
+        Shell::Bash | Shell::Zsh => format!("unset {}\n", key),
+        Shell::Fish => format!("set -e {}\n", key),
+    }
+}
+
+fn sh_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+fn fish_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\\', r"\\").replace('\'', r"\'"))
+}
@@ -1,3 +1,27 @@
+pub mod builder;
+pub mod cache;
 pub mod cli;
+pub mod detect;
+pub mod error;
+pub mod executor;
+pub mod export;
+pub mod hook;
+pub mod order;
+pub mod parallel;
+pub mod paths;
+pub mod profile;
+pub mod project;
+pub mod report;
+pub mod resolved_env;
 pub mod runner;
+pub mod selection;
+pub mod selftest;
+pub mod shims;
+pub mod toolchains;
+pub mod validate;
+pub mod workspace;
+pub mod wrap;
 pub mod yaml_config;
+
+pub use builder::{Builder, ConfigSource, Plan, ProjectConfig, StopNagging};
+pub use error::{Error, Result};
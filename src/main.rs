@@ -1,31 +1,337 @@
-mod cli;
-mod runner;
-mod yaml_config;
-
 use clap::Parser;
-use cli::Cli;
-use runner::Runner;
-use yaml_config::YamlConfig;
+use std::collections::HashMap;
+use stop_nagging::cli::{Cli, Command, ShimsCommand};
+use stop_nagging::export::{self, EnvFile};
+use stop_nagging::hook;
+use stop_nagging::paths::Paths;
+use stop_nagging::resolved_env::ResolvedEnv;
+use stop_nagging::selftest::{self, SelftestOptions, Verdict};
+use stop_nagging::shims;
+use stop_nagging::validate;
+use stop_nagging::wrap::{self, NagFilter};
+use stop_nagging::yaml_config::YamlConfig;
+use stop_nagging::{Error, StopNagging};
 
 fn main() {
     let cli = Cli::parse();
 
-    let config = if let Some(yaml_path) = cli.yaml {
-        match YamlConfig::from_file(yaml_path) {
-            Ok(config) => config,
+    let loaded = match &cli.yaml {
+        // `validate` checks the user's file, so it never falls back
+        Some(yaml_path) if matches!(cli.command, Some(Command::Validate)) => {
+            YamlConfig::from_file(yaml_path)
+        }
+        Some(yaml_path) => YamlConfig::from_file(yaml_path).or_else(|e| {
+            eprintln!("Warning: Failed to load custom YAML file: {}", e);
+            eprintln!("Falling back to default configuration");
+            YamlConfig::from_default()
+        }),
+        None => YamlConfig::from_default(),
+    };
+    let config = match loaded {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Error: Failed to load configuration: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    if let Some(Command::Validate) = cli.command {
+        match validate::check(&config) {
+            Ok(warnings) => {
+                for issue in &warnings {
+                    println!("{}", issue);
+                }
+                if warnings.is_empty() {
+                    println!("Configuration is valid");
+                }
+            }
+            Err(Error::Invalid { issues }) => {
+                for issue in &issues {
+                    println!("{}", issue);
+                }
+                std::process::exit(1);
+            }
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    if let Some(Command::Hook { shell }) = cli.command {
+        let exe = std::env::current_exe()
+            .map(|path| path.to_string_lossy().to_string())
+            .unwrap_or_else(|_| "stop-nagging".to_string());
+        print!("{}", hook::render_hook(shell, &exe));
+        return;
+    }
+
+    if let Some(Command::Wrap { command }) = &cli.command {
+        let filter = NagFilter::for_program(&config, &command[0]);
+        match wrap::run(command, &filter) {
+            Ok(code) => std::process::exit(code),
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(127);
+            }
+        }
+    }
+
+    if let Some(Command::Selftest { tool, installer }) = &cli.command {
+        let options = SelftestOptions {
+            installer: installer.clone(),
+            timeout: cli.timeout.unwrap_or(SelftestOptions::default().timeout),
+        };
+        let results = selftest::run(&config, tool, &options);
+        for result in &results {
+            println!("{}", result);
+        }
+        if results.is_empty() {
+            eprintln!("Warning: No tool with a nag_probe to test");
+        }
+        if results
+            .iter()
+            .any(|result| matches!(result.verdict, Verdict::Failed(_)))
+        {
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let shims_listed = match &cli.command {
+        Some(Command::Shims {
+            action: ShimsCommand::List { dir },
+        }) => Some(shims::list(dir)),
+        Some(Command::Shims {
+            action: ShimsCommand::Remove { dir },
+        }) => Some(shims::remove(dir)),
+        _ => None,
+    };
+    if let Some(result) = shims_listed {
+        match result {
+            Ok(paths) => {
+                for path in paths {
+                    println!("{}", path.display());
+                }
+            }
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    if cli.system && cli.root.is_none() && !is_root() {
+        eprintln!("Skipping: --system writes to /etc and must run as root (or use --root)");
+        return;
+    }
+
+    // Shims only need the resolved env, so nothing is run
+    let env_only = matches!(cli.command, Some(Command::Shims { .. }));
+    let shim_config = env_only.then(|| config.clone());
+
+    let mut builder = StopNagging::builder()
+        .ignore_tools(cli.ignore_tools.unwrap_or_default())
+        .ecosystems(cli.ecosystems.unwrap_or_default())
+        .skip_actions(cli.skip_actions.unwrap_or_default())
+        .only_actions(cli.only_actions.unwrap_or_default())
+        .force_env(cli.force_env)
+        .system(cli.system)
+        .recursive(cli.recursive)
+        .toolchains(!cli.no_toolchains)
+        .cache(!cli.no_cache)
+        .force(cli.force)
+        .dry_run(cli.dry_run || env_only)
+        .strict(false)
+        .verbose(cli.verbose);
+    builder = match &cli.profile {
+        Some(name) if config.profiles.contains_key(name) => builder.profile(name),
+        Some(name) => {
+            eprintln!("Warning: {}", Error::UnknownProfile(name.clone()));
+            builder.detect_profile()
+        }
+        None => builder.detect_profile(),
+    };
+    builder = builder.config(config);
+    if let Some(kinds) = cli.kinds {
+        builder = builder.kinds(kinds);
+    }
+    if let Some(scope) = cli.scope.scope() {
+        builder = builder.scope(scope);
+    }
+    // Detection starts here and walks up to the project root
+    match cli.project_dir {
+        Some(dir) if dir.is_dir() => builder = builder.current_dir(&dir).project_dir(dir),
+        Some(dir) => eprintln!(
+            "Warning: Project directory {} does not exist",
+            dir.display()
+        ),
+        None => {
+            if let Ok(dir) = std::env::current_dir() {
+                builder = builder.current_dir(dir);
+            }
+        }
+    }
+    if let Some(home) = cli.home {
+        builder = builder.home(home);
+    }
+    if let Some(root) = &cli.root {
+        builder = builder.root(root);
+    }
+    if let Some(jobs) = cli.jobs {
+        builder = builder.jobs(jobs);
+    }
+    if let Some(timeout) = cli.timeout {
+        builder = builder.timeout(timeout);
+    }
+
+    if let Some(Command::HookEnv { shell }) = cli.command {
+        let project = builder.project().unwrap_or_else(|e| exit_with(e));
+        // Nothing changed since the last run in this shell
+        let stamp = hook::stamp(&project.config, &project.dirs);
+        if std::env::var(hook::STAMP_VAR).is_ok_and(|previous| previous == stamp) {
+            return;
+        }
+        let previous = hook::previous_vars(std::env::var(hook::VARS_VAR).ok().as_deref());
+        // Vars exported by the hook itself don't count as already set
+        let base = hook::user_env(std::env::vars(), &previous);
+        let wanted = hook::project_env(&project.config, &project.dirs, &base);
+        print!(
+            "{}",
+            hook::render_update(shell, &previous, &wanted, &base, &stamp)
+        );
+        return;
+    }
+
+    if let Some(Command::Export { format, output }) = cli.command {
+        // Only the project's tools, resolved as if nothing were set, so the
+        // file doesn't depend on who exports it
+        let project = builder.project().unwrap_or_else(|e| exit_with(e));
+        let Some(root) = project.root.as_deref() else {
+            eprintln!("Warning: No project root found; nothing to export");
+            return;
+        };
+        let env = ResolvedEnv::resolve(
+            hook::project_tools(&project.config, &project.dirs),
+            &HashMap::new(),
+            false,
+        );
+        let path = output.unwrap_or_else(|| root.join(format.file_name()));
+        let existing = match std::fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => {
+                eprintln!("Error: Failed to read {}: {}", path.display(), e);
+                std::process::exit(1);
+            }
+        };
+        let merged = export::merge_env_file(&existing, &env, format);
+        if merged == existing {
+            if cli.verbose {
+                eprintln!("{} is up to date", path.display());
+            }
+            return;
+        }
+        if let Err(e) = std::fs::write(&path, merged) {
+            eprintln!("Error: Failed to write {}: {}", path.display(), e);
+            std::process::exit(1);
+        }
+        eprintln!("Updated {}", path.display());
+        if format == EnvFile::Envrc {
+            eprintln!("Run `direnv allow` to load the changes");
+        }
+        return;
+    }
+
+    let plan = builder.build().unwrap_or_else(|e| exit_with(e));
+    if cli.verbose {
+        for issue in plan.warnings() {
+            eprintln!("{}", issue);
+        }
+    }
+    let report = plan.apply().unwrap_or_else(|e| exit_with(e));
+    for message in &report.messages {
+        eprintln!("{}", message);
+    }
+
+    if let (
+        Some(Command::Shims {
+            action: ShimsCommand::Install { dir },
+        }),
+        Some(config),
+    ) = (&cli.command, &shim_config)
+    {
+        match shims::install(dir, config, &report) {
+            Ok(installed) => {
+                for path in &installed.skipped {
+                    eprintln!(
+                        "Warning: Not replacing {}, which isn't a stop-nagging shim",
+                        path.display()
+                    );
+                }
+                for path in &installed.written {
+                    println!("{}", path.display());
+                }
+                let on_path = std::env::var_os("PATH").is_some_and(|path| {
+                    std::env::split_paths(&path)
+                        .any(|p| p.canonicalize().ok() == dir.canonicalize().ok())
+                });
+                if !on_path {
+                    eprintln!(
+                        "Add {} to the front of PATH to use the shims",
+                        dir.display()
+                    );
+                }
+            }
             Err(e) => {
-                eprintln!("Warning: Failed to load custom YAML file: {}", e);
-                eprintln!("Falling back to default configuration");
-                YamlConfig::from_default().expect("Failed to load default configuration")
+                eprintln!("Error: Failed to install shims in {}: {}", dir.display(), e);
+                std::process::exit(1);
             }
         }
-    } else {
-        YamlConfig::from_default().expect("Failed to load default configuration")
+        return;
+    }
+
+    let paths = Paths {
+        home: None,
+        root: cli.root,
     };
+    if cli.dry_run {
+        print!("{}", report.render_plan());
+        if cli.system {
+            for file in [export::PROFILE_SCRIPT, export::ENVIRONMENT_D_FILE] {
+                println!("write {}", paths.system(file).display());
+            }
+        }
+    } else if cli.system {
+        match export::write_system_env(&report.env, &paths) {
+            Ok(files) if cli.verbose => {
+                for file in files {
+                    eprintln!("Wrote {}", file.display());
+                }
+            }
+            Ok(_) => {}
+            Err(e) => eprintln!("Warning: Failed to write system-wide env files: {}", e),
+        }
+    }
+
+    if cli.print_env {
+        print!("{}", export::render_posix(&report.env));
+    }
+}
 
-    let ignore_tools = cli.ignore_tools.unwrap_or_default();
-    let ecosystems = cli.ecosystems.unwrap_or_default();
+fn exit_with(error: Error) -> ! {
+    eprintln!("Error: {}", error);
+    std::process::exit(1);
+}
+
+#[cfg(unix)]
+fn is_root() -> bool {
+    unsafe { libc::geteuid() == 0 }
+}
 
-    let runner = Runner::new(config, ignore_tools, ecosystems, cli.verbose);
-    runner.run();
+#[cfg(not(unix))]
+fn is_root() -> bool {
+    false
 }
@@ -0,0 +1,90 @@
+use crate::error::{Error, Result};
+use crate::yaml_config::{Tool, YamlConfig};
+use std::collections::HashMap;
+
+/// A tool together with the ecosystem it was declared in.
+#[derive(Debug, Clone, Copy)]
+pub struct ToolRef<'a> {
+    pub ecosystem: &'a str,
+    pub tool: &'a Tool,
+}
+
+/// Every tool in declaration order, ecosystem by ecosystem.
+pub fn declaration_order(config: &YamlConfig) -> Vec<ToolRef<'_>> {
+    config
+        .ecosystems
+        .iter()
+        .flat_map(|(ecosystem, eco)| {
+            eco.tools.iter().map(move |tool| ToolRef {
+                ecosystem: ecosystem.as_str(),
+                tool,
+            })
+        })
+        .collect()
+}
+
+/// Orders tools so that every tool runs after the tools listed in its `after:`
+/// field. Otherwise the declaration order is kept. Unknown names in `after:`
+/// are ignored here and reported by validation.
+///
+/// Fails with [`Error::Cycle`] if tools wait for each other.
+pub fn tool_order(config: &YamlConfig) -> Result<Vec<ToolRef<'_>>> {
+    let tools = declaration_order(config);
+    let index: HashMap<&str, usize> = tools
+        .iter()
+        .enumerate()
+        .map(|(i, t)| (t.tool.name.as_str(), i))
+        .collect();
+
+    let deps: Vec<Vec<usize>> = tools
+        .iter()
+        .map(|t| {
+            t.tool
+                .after
+                .iter()
+                .filter_map(|name| index.get(name.as_str()).copied())
+                .collect()
+        })
+        .collect();
+
+    let mut placed = vec![false; tools.len()];
+    let mut ordered = Vec::with_capacity(tools.len());
+
+    // Repeatedly take the earliest declared tool whose dependencies are placed
+    while ordered.len() < tools.len() {
+        let next = (0..tools.len()).find(|&i| !placed[i] && deps[i].iter().all(|&d| placed[d]));
+        match next {
+            Some(i) => {
+                placed[i] = true;
+                ordered.push(tools[i]);
+            }
+            None => return Err(Error::Cycle(find_cycle(&tools, &deps, &placed))),
+        }
+    }
+
+    Ok(ordered)
+}
+
+fn find_cycle(tools: &[ToolRef<'_>], deps: &[Vec<usize>], placed: &[bool]) -> Vec<String> {
+    // Every unplaced tool has an unplaced dependency, so following them must loop
+    let start = placed.iter().position(|p| !p).unwrap_or(0);
+    let mut path = vec![start];
+    let mut current = start;
+    loop {
+        let next = deps[current]
+            .iter()
+            .copied()
+            .find(|&d| !placed[d])
+            .unwrap_or(start);
+        if let Some(pos) = path.iter().position(|&p| p == next) {
+            let mut cycle: Vec<String> = path[pos..]
+                .iter()
+                .map(|&i| tools[i].tool.name.clone())
+                .collect();
+            cycle.push(tools[next].tool.name.clone());
+            return cycle;
+        }
+        path.push(next);
+        current = next;
+    }
+}
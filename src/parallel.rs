@@ -0,0 +1,71 @@
+use std::sync::{Condvar, Mutex};
+use std::thread;
+
+struct State<R> {
+    started: Vec<bool>,
+    results: Vec<Option<R>>,
+}
+
+/// Runs `f` over `items` on up to `jobs` threads and returns the results in
+/// the order of `items`.
+///
+/// `deps[i]` lists the indices that must finish before item `i` starts. Only
+/// indices lower than `i` are honored, so a bad dependency list can't deadlock.
+pub fn run_ordered<T, R, F>(items: &[T], deps: &[Vec<usize>], jobs: usize, f: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+{
+    let deps: Vec<Vec<usize>> = (0..items.len())
+        .map(|i| {
+            deps.get(i)
+                .map(|d| d.iter().copied().filter(|&j| j < i).collect())
+                .unwrap_or_default()
+        })
+        .collect();
+
+    let state = Mutex::new(State {
+        started: vec![false; items.len()],
+        results: (0..items.len()).map(|_| None).collect(),
+    });
+    let finished = Condvar::new();
+    let workers = jobs.max(1).min(items.len());
+
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                let index = {
+                    let mut state = state.lock().unwrap();
+                    loop {
+                        if state.started.iter().all(|&s| s) {
+                            return;
+                        }
+                        let ready = (0..items.len()).find(|&i| {
+                            !state.started[i] && deps[i].iter().all(|&d| state.results[d].is_some())
+                        });
+                        match ready {
+                            Some(i) => {
+                                state.started[i] = true;
+                                break i;
+                            }
+                            None => state = finished.wait(state).unwrap(),
+                        }
+                    }
+                };
+
+                let result = f(&items[index]);
+                state.lock().unwrap().results[index] = Some(result);
+                finished.notify_all();
+            });
+        }
+    });
+
+    state
+        .into_inner()
+        .unwrap()
+        .results
+        .into_iter()
+        .map(|r| r.expect("every item is run exactly once"))
+        .collect()
+}
@@ -0,0 +1,84 @@
+use crate::yaml_config::CommandLine;
+use std::path::{Component, Path, PathBuf};
+
+/// Programs whose system-wide config follows the env vars of
+/// [`Paths::child_env`]. Others, like `pip config --global` or
+/// `conda config --system`, always write to the real locations.
+const ROOT_AWARE_PROGRAMS: &[&str] = &["git", "npm"];
+
+/// Where stop-nagging and the commands it runs write files. Both default to
+/// the real locations; `--home` and `--root` move them, e.g. into an image
+/// that is being built.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Paths {
+    /// Home directory for user-level files
+    pub home: Option<PathBuf>,
+    /// Prefix for system-level paths such as `/etc/gitconfig`
+    pub root: Option<PathBuf>,
+}
+
+impl Paths {
+    /// Resolves an absolute system path such as `/etc/npmrc` under the root.
+    pub fn system(&self, path: impl AsRef<Path>) -> PathBuf {
+        let path = path.as_ref();
+        match &self.root {
+            Some(root) => root.join(relative(path)),
+            None => path.to_path_buf(),
+        }
+    }
+
+    /// The home directory to use: `--home`, or the real home under `--root`.
+    pub fn resolve_home(
+        home: Option<PathBuf>,
+        root: Option<&Path>,
+        real_home: Option<&Path>,
+    ) -> Option<PathBuf> {
+        home.or_else(|| Some(root?.join(relative(real_home?))))
+    }
+
+    /// Env vars that point child commands at these paths. Empty when
+    /// nothing is moved.
+    pub fn child_env(&self) -> Vec<(String, String)> {
+        let mut vars = Vec::new();
+        let mut set = |key: &str, path: PathBuf| {
+            vars.push((key.to_string(), path.to_string_lossy().to_string()));
+        };
+
+        if let Some(home) = &self.home {
+            set("HOME", home.clone());
+            set("USERPROFILE", home.clone());
+            set("XDG_CONFIG_HOME", home.join(".config"));
+            set("XDG_DATA_HOME", home.join(".local/share"));
+            set("XDG_STATE_HOME", home.join(".local/state"));
+            set("XDG_CACHE_HOME", home.join(".cache"));
+        }
+        if self.root.is_some() {
+            // Tools that allow it are pointed at system config files under the root
+            set("GIT_CONFIG_SYSTEM", self.system("/etc/gitconfig"));
+            set("NPM_CONFIG_GLOBALCONFIG", self.system("/etc/npmrc"));
+        }
+        vars
+    }
+
+    /// Whether the `system:` command `cmd` writes its config under the root.
+    /// Always true without `--root`.
+    pub fn redirects_system_config(&self, cmd: &CommandLine) -> bool {
+        if self.root.is_none() {
+            return true;
+        }
+        let program = match cmd {
+            CommandLine::Shell(line) => line.split_whitespace().next(),
+            CommandLine::Argv(argv) => argv.first().map(String::as_str),
+        };
+        program
+            .and_then(|program| Path::new(program).file_stem())
+            .is_some_and(|name| ROOT_AWARE_PROGRAMS.iter().any(|p| name == *p))
+    }
+}
+
+/// Drops the root and prefix of an absolute path so it can be joined.
+fn relative(path: &Path) -> PathBuf {
+    path.components()
+        .filter(|c| !matches!(c, Component::RootDir | Component::Prefix(_)))
+        .collect()
+}
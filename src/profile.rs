@@ -0,0 +1,103 @@
+use crate::error::{Error, Result};
+use crate::yaml_config::{Profile, YamlConfig};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Env vars set by well-known CI services.
+const CI_VARS: &[&str] = &[
+    "CI",
+    "GITHUB_ACTIONS",
+    "GITLAB_CI",
+    "BUILDKITE",
+    "CIRCLECI",
+    "TRAVIS",
+    "JENKINS_URL",
+    "TF_BUILD",
+    "TEAMCITY_VERSION",
+    "BITBUCKET_BUILD_NUMBER",
+    "CODEBUILD_BUILD_ID",
+];
+
+/// Files that container runtimes create.
+const CONTAINER_MARKERS: &[&str] = &["/.dockerenv", "/run/.containerenv"];
+
+/// Facts about the machine that decide which profile is picked automatically.
+#[derive(Debug, Clone, Default)]
+pub struct Detected {
+    /// The CI env var that was found, if any
+    pub ci_var: Option<String>,
+    /// The container marker file that was found, if any
+    pub container_marker: Option<String>,
+}
+
+impl Detected {
+    pub fn from_system() -> Self {
+        let vars: HashMap<String, String> = std::env::vars().collect();
+        Detected {
+            ci_var: detect_ci(&vars),
+            container_marker: CONTAINER_MARKERS
+                .iter()
+                .find(|marker| Path::new(marker).exists())
+                .map(|marker| marker.to_string()),
+        }
+    }
+}
+
+/// Returns the first CI env var that is set to something other than a false value.
+pub fn detect_ci(vars: &HashMap<String, String>) -> Option<String> {
+    CI_VARS
+        .iter()
+        .find(|name| {
+            vars.get(**name).is_some_and(|value| {
+                let value = value.trim().to_ascii_lowercase();
+                !matches!(value.as_str(), "" | "0" | "false" | "no" | "off")
+            })
+        })
+        .map(|name| name.to_string())
+}
+
+/// The profile in use and why it was chosen.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ActiveProfile {
+    pub name: String,
+    pub profile: Profile,
+    pub reason: String,
+}
+
+/// Picks the profile to use: the `requested` one, otherwise `ci` on CI,
+/// `container` inside a container and `dev` everywhere else. Automatic
+/// choices are only made if the profile exists.
+///
+/// Fails with [`Error::UnknownProfile`] if the requested profile doesn't exist.
+pub fn choose(
+    config: &YamlConfig,
+    requested: Option<&str>,
+    detected: &Detected,
+) -> Result<Option<ActiveProfile>> {
+    let active = |name: &str, reason: String| {
+        config.profiles.get(name).map(|profile| ActiveProfile {
+            name: name.to_string(),
+            profile: profile.clone(),
+            reason,
+        })
+    };
+
+    if let Some(name) = requested {
+        return active(name, "selected with --profile".to_string())
+            .map(Some)
+            .ok_or_else(|| Error::UnknownProfile(name.to_string()));
+    }
+
+    let chosen = detected
+        .ci_var
+        .as_ref()
+        .and_then(|var| active("ci", format!("{} is set", var)))
+        .or_else(|| {
+            detected
+                .container_marker
+                .as_ref()
+                .and_then(|marker| active("container", format!("found {}", marker)))
+        })
+        .or_else(|| active("dev", "default".to_string()));
+    Ok(chosen)
+}
@@ -0,0 +1,47 @@
+use std::path::{Path, PathBuf};
+
+/// Files that mark the root of a project when there is no git repository.
+const MARKERS: &[&str] = &[
+    "package.json",
+    "Cargo.toml",
+    "pyproject.toml",
+    "go.mod",
+    "pom.xml",
+    "build.gradle",
+    "build.gradle.kts",
+    "settings.gradle",
+    "settings.gradle.kts",
+    "composer.json",
+    "Gemfile",
+];
+
+/// Finds the root of the project containing `start`: the nearest directory
+/// with a `.git` entry, or else the nearest one with a marker file such as
+/// `package.json`.
+pub fn find_root(start: &Path) -> Option<PathBuf> {
+    let git_root = start
+        .ancestors()
+        .find(|dir| dir.join(".git").exists())
+        .map(Path::to_path_buf);
+
+    git_root.or_else(|| {
+        start
+            .ancestors()
+            .find(|dir| MARKERS.iter().any(|marker| dir.join(marker).is_file()))
+            .map(Path::to_path_buf)
+    })
+}
+
+/// The directories to check `detect:` blocks in, nearest first: `start` and
+/// its parents up to and including `root`. Just `root` if `start` isn't
+/// inside it.
+pub fn search_dirs(start: &Path, root: &Path) -> Vec<PathBuf> {
+    if !start.starts_with(root) {
+        return vec![root.to_path_buf()];
+    }
+    start
+        .ancestors()
+        .take_while(|dir| dir.starts_with(root))
+        .map(Path::to_path_buf)
+        .collect()
+}
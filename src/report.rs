@@ -0,0 +1,163 @@
+use crate::error::Error;
+use crate::resolved_env::{EnvStatus, ResolvedEnv};
+use crate::toolchains::Toolchain;
+use std::fmt::Write;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+#[derive(Debug, Clone)]
+pub enum CommandOutcome {
+    Success,
+    /// The command ran and exited unsuccessfully. `status` is `None` if it
+    /// was killed by a signal; `stderr` is empty for interactive commands,
+    /// which print to the terminal.
+    Failed {
+        status: Option<i32>,
+        stderr: String,
+    },
+    /// The command couldn't be started or waited for, e.g. because its
+    /// program isn't installed
+    SpawnFailed(Arc<Error>),
+    /// Killed after running longer than the given timeout
+    TimedOut(Duration),
+    /// The command asked a question; holds the prompt it printed
+    NeedsInteraction(String),
+    /// Not run, because this is a dry run or there is no project root
+    NotRun,
+}
+
+impl CommandOutcome {
+    /// Why the command failed, for messages: what it printed to stderr, its
+    /// exit status, or the error that kept it from running. `None` for the
+    /// other outcomes.
+    pub fn failure(&self) -> Option<String> {
+        match self {
+            CommandOutcome::Failed { stderr, .. } if !stderr.trim().is_empty() => {
+                Some(stderr.trim().to_string())
+            }
+            CommandOutcome::Failed {
+                status: Some(code), ..
+            } => Some(format!("exit status {}", code)),
+            CommandOutcome::Failed { status: None, .. } => Some("killed by a signal".to_string()),
+            CommandOutcome::SpawnFailed(error) => Some(error.to_string()),
+            _ => None,
+        }
+    }
+}
+
+// `Error` can't be compared, so spawn failures compare by their message
+impl PartialEq for CommandOutcome {
+    fn eq(&self, other: &Self) -> bool {
+        use CommandOutcome::*;
+        match (self, other) {
+            (Success, Success) | (NotRun, NotRun) => true,
+            (
+                Failed { status, stderr },
+                Failed {
+                    status: other_status,
+                    stderr: other_stderr,
+                },
+            ) => status == other_status && stderr == other_stderr,
+            (SpawnFailed(a), SpawnFailed(b)) => a.to_string() == b.to_string(),
+            (TimedOut(a), TimedOut(b)) => a == b,
+            (NeedsInteraction(a), NeedsInteraction(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for CommandOutcome {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandReport {
+    /// Action ID, e.g. `yarn.enableGlobalCache`
+    pub id: String,
+    pub command: String,
+    pub outcome: CommandOutcome,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToolStatus {
+    Applied,
+    NotAvailable,
+    /// Applied on an earlier run with the same fingerprint; nothing was run
+    UpToDate,
+}
+
+/// What happened to a single tool. Messages are buffered here so that
+/// tools running in parallel don't interleave their output.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ToolReport {
+    pub ecosystem: String,
+    pub tool: String,
+    pub status: ToolStatus,
+    /// The workspace member this report is for; `None` for the main project
+    pub project: Option<PathBuf>,
+    /// The installed toolchain this report is for; `None` for the one on `PATH`
+    pub toolchain: Option<Toolchain>,
+    pub commands: Vec<CommandReport>,
+    pub messages: Vec<String>,
+}
+
+/// The result of a run, with tools in execution order.
+#[derive(Debug, Clone, Default)]
+pub struct Report {
+    pub env: ResolvedEnv,
+    pub tools: Vec<ToolReport>,
+    /// Warnings that aren't about a single tool, e.g. an ordering cycle or a
+    /// cache that couldn't be written
+    pub messages: Vec<String>,
+}
+
+impl Report {
+    pub fn tool(&self, name: &str) -> Option<&ToolReport> {
+        self.tools.iter().find(|t| t.tool == name)
+    }
+
+    /// Lists every action of the available tools with its ID, for `--dry-run`.
+    pub fn render_plan(&self) -> String {
+        let mut out = String::new();
+        for tool in &self.tools {
+            let name = match (&tool.project, &tool.toolchain) {
+                (Some(dir), _) => {
+                    format!("{} ({}, in {})", tool.tool, tool.ecosystem, dir.display())
+                }
+                (None, Some(toolchain)) => {
+                    format!("{} ({}, for {})", tool.tool, tool.ecosystem, toolchain)
+                }
+                (None, None) => format!("{} ({})", tool.tool, tool.ecosystem),
+            };
+            match tool.status {
+                ToolStatus::NotAvailable => {
+                    writeln!(out, "{}: not available", name).unwrap();
+                    continue;
+                }
+                // The env is still applied; only the commands are skipped
+                ToolStatus::UpToDate => writeln!(out, "{}: up to date", name).unwrap(),
+                ToolStatus::Applied => writeln!(out, "{}", name).unwrap(),
+            }
+            // The env is shared, so list it with the main run only
+            let env = self.env.iter().filter(|(_, e)| e.tool == tool.tool);
+            let shares_env = tool.project.is_none() && tool.toolchain.is_none();
+            for (key, entry) in env.filter(|_| shares_env) {
+                let status = match entry.status {
+                    EnvStatus::Set | EnvStatus::Overridden => "set",
+                    EnvStatus::Appended => "append",
+                    EnvStatus::AlreadySet => "already set",
+                    EnvStatus::Kept => "keep existing",
+                };
+                writeln!(
+                    out,
+                    "  {}  env {}={} ({})",
+                    entry.id, key, entry.value, status
+                )
+                .unwrap();
+            }
+            for command in &tool.commands {
+                writeln!(out, "  {}  run {}", command.id, command.command).unwrap();
+            }
+        }
+        out
+    }
+}
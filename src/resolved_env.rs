@@ -0,0 +1,201 @@
+use crate::yaml_config::{EnvMode, EnvPolicy, EnvSeparator, Tool};
+use std::collections::{BTreeMap, HashMap};
+
+/// How a resolved variable relates to the environment stop-nagging was started with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EnvStatus {
+    /// Not set before; stop-nagging sets it
+    Set,
+    /// Already set to the desired value; nothing to do
+    AlreadySet,
+    /// Set to a different value that is kept according to the policy
+    Kept,
+    /// Set to a different value that stop-nagging replaces
+    Overridden,
+    /// Set to a token list that stop-nagging adds its token to
+    Appended,
+}
+
+/// A single environment variable as resolved for this run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnvEntry {
+    /// Action ID, e.g. `yarn.YARN_IGNORE_PATH`
+    pub id: String,
+    /// The value the owning tool wants the variable to have. For
+    /// `append_token` entries this is the token.
+    pub value: String,
+    /// The value child commands receive, after merging with the existing value.
+    pub effective: String,
+    pub mode: EnvMode,
+    pub separator: EnvSeparator,
+    /// Name of the tool that claimed the variable first.
+    pub tool: String,
+    /// Whether the variable was already present in the base environment.
+    pub pre_existing: bool,
+    /// The value found in the base environment, if any.
+    pub existing: Option<String>,
+    pub status: EnvStatus,
+}
+
+/// Two tools asking for the same variable. The first tool keeps it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnvConflict {
+    pub key: String,
+    pub kept_tool: String,
+    pub kept_value: String,
+    pub dropped_tool: String,
+    pub dropped_value: String,
+}
+
+/// The environment stop-nagging wants to apply, computed up front instead of
+/// mutating the process environment while tools are being processed.
+#[derive(Debug, Clone, Default)]
+pub struct ResolvedEnv {
+    entries: BTreeMap<String, EnvEntry>,
+    conflicts: Vec<EnvConflict>,
+}
+
+impl ResolvedEnv {
+    /// Resolves the env of `tools` against `base`, in the order the tools are given.
+    /// With `force_env`, every pre-existing value with a different value is overridden.
+    pub fn resolve<'a, I>(tools: I, base: &HashMap<String, String>, force_env: bool) -> Self
+    where
+        I: IntoIterator<Item = &'a Tool>,
+    {
+        let mut resolved = ResolvedEnv::default();
+
+        for tool in tools {
+            for (key, setting) in &tool.env {
+                if let Some(existing) = resolved.entries.get(key) {
+                    resolved.conflicts.push(EnvConflict {
+                        key: key.clone(),
+                        kept_tool: existing.tool.clone(),
+                        kept_value: existing.value.clone(),
+                        dropped_tool: tool.name.clone(),
+                        dropped_value: setting.value.clone(),
+                    });
+                    continue;
+                }
+
+                let existing = base.get(key).cloned();
+                let policy = if force_env {
+                    EnvPolicy::Override
+                } else {
+                    setting.policy
+                };
+                let separator = setting.separator.as_str();
+                let (status, effective) = match (&existing, setting.mode) {
+                    (None, _) => (EnvStatus::Set, setting.value.clone()),
+                    (Some(current), EnvMode::AppendToken) => {
+                        if has_token(current, &setting.value, separator) {
+                            (EnvStatus::AlreadySet, current.clone())
+                        } else if current.trim().is_empty() {
+                            (EnvStatus::Appended, setting.value.clone())
+                        } else {
+                            let merged = format!("{}{}{}", current, separator, setting.value);
+                            (EnvStatus::Appended, merged)
+                        }
+                    }
+                    (Some(current), EnvMode::Set) if *current == setting.value => {
+                        (EnvStatus::AlreadySet, current.clone())
+                    }
+                    (Some(current), EnvMode::Set) => {
+                        if should_override(policy, current, &setting.value) {
+                            (EnvStatus::Overridden, setting.value.clone())
+                        } else {
+                            (EnvStatus::Kept, current.clone())
+                        }
+                    }
+                };
+
+                resolved.entries.insert(
+                    key.clone(),
+                    EnvEntry {
+                        id: tool.env_action_id(key),
+                        value: setting.value.clone(),
+                        effective,
+                        mode: setting.mode,
+                        separator: setting.separator,
+                        tool: tool.name.clone(),
+                        pre_existing: existing.is_some(),
+                        existing,
+                        status,
+                    },
+                );
+            }
+        }
+
+        resolved
+    }
+
+    /// Resolves the env of `tools` against the current process environment.
+    pub fn from_process_env<'a, I>(tools: I, force_env: bool) -> Self
+    where
+        I: IntoIterator<Item = &'a Tool>,
+    {
+        let base: HashMap<String, String> = std::env::vars().collect();
+        Self::resolve(tools, &base, force_env)
+    }
+
+    pub fn get(&self, key: &str) -> Option<&EnvEntry> {
+        self.entries.get(key)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &EnvEntry)> {
+        self.entries.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn conflicts(&self) -> &[EnvConflict] {
+        &self.conflicts
+    }
+
+    /// Entries that change the inherited environment.
+    pub fn entries_to_set(&self) -> impl Iterator<Item = (&String, &EnvEntry)> {
+        self.entries.iter().filter(|(_, entry)| {
+            matches!(
+                entry.status,
+                EnvStatus::Set | EnvStatus::Overridden | EnvStatus::Appended
+            )
+        })
+    }
+
+    /// Variables that should be added on top of the inherited environment,
+    /// with their effective values. Pre-existing variables are only included
+    /// when they are overridden or appended to.
+    pub fn vars_to_set(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.entries_to_set()
+            .map(|(key, entry)| (key.as_str(), entry.effective.as_str()))
+    }
+}
+
+fn should_override(policy: EnvPolicy, current: &str, desired: &str) -> bool {
+    match policy {
+        EnvPolicy::Keep => false,
+        EnvPolicy::Override => true,
+        // Only replace values that clearly flip the switch the other way
+        EnvPolicy::OverrideIfEnabled => match (as_bool(current), as_bool(desired)) {
+            (Some(current), Some(desired)) => current != desired,
+            _ => false,
+        },
+    }
+}
+
+fn has_token(list: &str, token: &str, separator: &str) -> bool {
+    if separator.trim().is_empty() {
+        list.split_whitespace().any(|existing| existing == token)
+    } else {
+        list.split(separator).any(|existing| existing == token)
+    }
+}
+
+fn as_bool(value: &str) -> Option<bool> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Some(true),
+        "0" | "false" | "no" | "off" | "" => Some(false),
+        _ => None,
+    }
+}
@@ -1,15 +1,58 @@
-use crate::yaml_config::YamlConfig;
-use std::env;
-use std::process::Command;
+use crate::cache::{self, Cache};
+use crate::detect;
+use crate::executor::{self, ExecOptions, Executor, ProcessExecutor};
+use crate::order::{self, ToolRef};
+use crate::parallel;
+use crate::paths::Paths;
+use crate::project;
+use crate::report::{CommandOutcome, CommandReport, Report, ToolReport, ToolStatus};
+use crate::resolved_env::{EnvStatus, ResolvedEnv};
+use crate::selection::{self, Selection};
+use crate::toolchains::Toolchain;
+use crate::yaml_config::{ActionScope, Detect, Tool, YamlConfig};
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
 
 pub struct Runner {
     config: YamlConfig,
     ignore_tools: Vec<String>,
     ecosystems: Vec<String>,
     verbose: bool,
+    force_env: bool,
+    jobs: usize,
+    timeout: Duration,
+    selection: Selection,
+    dry_run: bool,
+    project_root: Option<PathBuf>,
+    start_dir: Option<PathBuf>,
+    workspace_members: Vec<PathBuf>,
+    toolchains: Vec<Toolchain>,
+    paths: Paths,
+    system: bool,
+    cache: Option<Mutex<Cache>>,
+    force: bool,
+    executor: Arc<dyn Executor>,
+}
+
+/// Where a tool is applied: the project commands run in and the toolchain
+/// whose binaries are used.
+#[derive(Clone, Copy)]
+struct Target<'a> {
+    root: Option<&'a Path>,
+    toolchain: Option<&'a Toolchain>,
+    /// The workspace member being applied to; `None` for the main project,
+    /// even if `root` is a subdirectory of it
+    member: Option<&'a Path>,
 }
 
 impl Runner {
+    /// Applications embedding stop-nagging should use
+    /// [`StopNagging::builder`](crate::StopNagging::builder) instead, which
+    /// loads the configuration and applies profiles too.
     pub fn new(
         config: YamlConfig,
         ignore_tools: Vec<String>,
@@ -21,85 +64,744 @@ impl Runner {
             ignore_tools,
             ecosystems,
             verbose,
+            force_env: false,
+            jobs: default_jobs(),
+            timeout: executor::DEFAULT_TIMEOUT,
+            selection: Selection::default(),
+            dry_run: false,
+            project_root: None,
+            start_dir: None,
+            workspace_members: vec![],
+            toolchains: vec![],
+            paths: Paths::default(),
+            system: false,
+            cache: None,
+            force: false,
+            executor: Arc::new(ProcessExecutor),
         }
     }
 
-    pub fn run(&self) {
-        for (ecosystem_name, ecosystem) in &self.config.ecosystems {
-            // Skip if ecosystems is not empty and doesn't contain this ecosystem
-            if !self.ecosystems.is_empty() && !self.ecosystems.contains(ecosystem_name) {
-                continue;
+    /// Print progress and env details to stderr.
+    pub(crate) fn with_verbose(mut self, verbose: bool) -> Self {
+        self.verbose = verbose;
+        self
+    }
+
+    /// Override pre-existing env vars regardless of their policy.
+    pub fn with_force_env(mut self, force_env: bool) -> Self {
+        self.force_env = force_env;
+        self
+    }
+
+    /// Maximum number of tools processed at the same time.
+    pub fn with_jobs(mut self, jobs: usize) -> Self {
+        self.jobs = jobs.max(1);
+        self
+    }
+
+    /// Default timeout for commands that don't declare their own.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Restrict which env entries and commands are applied.
+    pub fn with_selection(mut self, selection: Selection) -> Self {
+        self.selection = selection;
+        self
+    }
+
+    /// Resolve everything but don't run any tool commands.
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Directory that project-scoped commands run in and `detect:` blocks are
+    /// checked against. Without one, project commands are skipped.
+    pub fn with_project_root(mut self, project_root: Option<PathBuf>) -> Self {
+        self.project_root = project_root;
+        self
+    }
+
+    /// Directory inside the project to check `detect:` blocks from, usually
+    /// the current one. Its parents up to the project root are checked too,
+    /// and each tool applies in the nearest directory it's detected in, e.g.
+    /// `apps/web` of a monorepo. Defaults to the project root.
+    pub fn with_start_dir(mut self, dir: Option<PathBuf>) -> Self {
+        self.start_dir = dir;
+        self
+    }
+
+    /// Workspace members that get the project actions too, each in its own directory.
+    pub fn with_workspace_members(mut self, members: Vec<PathBuf>) -> Self {
+        self.workspace_members = members;
+        self
+    }
+
+    /// Installed toolchains, e.g. from nvm, that get the global commands of
+    /// their tools too. Commands run with the toolchain's `bin` first on `PATH`.
+    pub fn with_toolchains(mut self, toolchains: Vec<Toolchain>) -> Self {
+        self.toolchains = toolchains;
+        self
+    }
+
+    /// Home and root directories that child commands should write to.
+    pub fn with_paths(mut self, paths: Paths) -> Self {
+        self.paths = paths;
+        self
+    }
+
+    /// Run the `system:` variant of global commands instead of the per-user
+    /// command; global commands without one are skipped.
+    pub fn with_system(mut self, system: bool) -> Self {
+        self.system = system;
+        self
+    }
+
+    /// Skip tools whose fingerprint matches the one recorded in `cache`, and
+    /// record the fingerprints of tools that were applied.
+    pub fn with_cache(mut self, cache: Cache) -> Self {
+        self.cache = Some(Mutex::new(cache));
+        self
+    }
+
+    /// Apply every tool even if the cache says it is up to date.
+    pub fn with_force(mut self, force: bool) -> Self {
+        self.force = force;
+        self
+    }
+
+    /// Run commands with `executor` instead of starting processes directly.
+    pub fn with_executor(mut self, executor: Arc<dyn Executor>) -> Self {
+        self.executor = executor;
+        self
+    }
+
+    /// Applies every selected tool and returns what happened, with tools in
+    /// execution order. Workspace members follow the main project.
+    pub fn run(&self) -> Report {
+        self.execute(&self.plan())
+    }
+
+    /// Works out which tools apply where and resolves the env, without running
+    /// any tool command.
+    pub(crate) fn plan(&self) -> RunPlan {
+        let config = self.selection.apply(&self.config);
+        let mut messages = Vec::new();
+        if let Err(e) = order::tool_order(&config) {
+            messages.push(format!("Warning: {}; using declaration order", e));
+        }
+        let dirs = self.search_dirs();
+        let candidates = self.candidate_tools(&config, &dirs);
+
+        // Probe all candidates up front; the env depends on which tools exist
+        let found = self.probe(&config, &candidates, &dirs);
+        let tools = candidates
+            .iter()
+            .zip(&found)
+            .filter(|(_, found)| found.is_some())
+            .map(|(t, _)| t.tool);
+        let env = ResolvedEnv::from_process_env(tools, self.force_env);
+
+        let mut groups = vec![PlannedGroup::new(
+            self.project_root.clone(),
+            None,
+            &candidates,
+            &found,
+        )];
+
+        // Members only get project actions; everything global is applied once
+        let project_config = selection::restrict_scope(&config, ActionScope::Project);
+        for member in &self.workspace_members {
+            let dirs = [member.clone()];
+            let candidates = self.candidate_tools(&project_config, &dirs);
+            let found = self.probe(&project_config, &candidates, &dirs);
+            groups.push(PlannedGroup::new(
+                Some(member.clone()),
+                None,
+                &candidates,
+                &found,
+            ));
+        }
+
+        // Commands like `npm config set` only reach the toolchain first on PATH
+        if !self.toolchains.is_empty() {
+            let global_config = selection::restrict_scope(&config, ActionScope::Global);
+            let candidates = self.candidate_tools(&global_config, &dirs);
+            for toolchain in &self.toolchains {
+                let found: Vec<Option<Option<PathBuf>>> = candidates
+                    .iter()
+                    .map(|t| in_toolchain(t.tool, toolchain).then(|| self.project_root.clone()))
+                    .collect();
+                groups.push(PlannedGroup::new(
+                    self.project_root.clone(),
+                    Some(toolchain.clone()),
+                    &candidates,
+                    &found,
+                ));
+            }
+        }
+
+        RunPlan {
+            env,
+            groups,
+            messages,
+        }
+    }
+
+    /// Applies the tools of `plan`, or only reports them in a dry run.
+    pub(crate) fn execute(&self, plan: &RunPlan) -> Report {
+        self.print_env_status(&plan.env);
+
+        let mut messages = plan.messages.clone();
+        let mut reports = Vec::new();
+        for (index, group) in plan.groups.iter().enumerate() {
+            let candidates: Vec<ToolRef> = group
+                .tools
+                .iter()
+                .map(|planned| ToolRef {
+                    ecosystem: &planned.ecosystem,
+                    tool: &planned.tool,
+                })
+                .collect();
+            let target = Target {
+                root: group.root.as_deref(),
+                toolchain: group.toolchain.as_ref(),
+                member: self.member_dir(group.root.as_deref()),
+            };
+            let targets: Vec<Option<Target>> = group
+                .tools
+                .iter()
+                .map(|planned| {
+                    planned.found.as_ref().map(|root| Target {
+                        root: root.as_deref(),
+                        ..target
+                    })
+                })
+                .collect();
+            let applied = self.apply_tools(&candidates, &targets, &plan.env, target);
+            // Only the main project reports the tools it doesn't have
+            if index == 0 {
+                reports.extend(applied);
+            } else {
+                reports.extend(
+                    applied
+                        .into_iter()
+                        .filter(|report| report.status != ToolStatus::NotAvailable),
+                );
             }
+        }
 
-            // Check if ecosystem is available
-            if let Some(check_cmd) = &ecosystem.check_ecosystem {
-                if !self.check_command(check_cmd) {
-                    if self.verbose {
-                        println!("Ecosystem {} not available, skipping", ecosystem_name);
+        if self.verbose {
+            for report in &reports {
+                for message in &report.messages {
+                    eprintln!("{}", message);
+                }
+            }
+            for toolchain in &self.toolchains {
+                let tools: Vec<&str> = reports
+                    .iter()
+                    .filter(|r| r.toolchain.as_ref() == Some(toolchain))
+                    .map(|r| r.tool.as_str())
+                    .collect();
+                if tools.is_empty() {
+                    eprintln!("Toolchain {}: nothing to apply", toolchain);
+                } else {
+                    eprintln!("Toolchain {}: applied {}", toolchain, tools.join(", "));
+                }
+            }
+        }
+
+        if let Some(cache) = self.cache.as_ref().filter(|_| !self.dry_run) {
+            let cache = cache.lock().unwrap();
+            if let Err(e) = cache.save() {
+                messages.push(format!("Warning: Failed to write cache: {}", e));
+            }
+        }
+
+        Report {
+            env: plan.env.clone(),
+            tools: reports,
+            messages,
+        }
+    }
+
+    /// The project root, and the directories between it and the start dir,
+    /// nearest first. Empty without a project root.
+    fn search_dirs(&self) -> Vec<PathBuf> {
+        let Some(root) = &self.project_root else {
+            return vec![];
+        };
+        project::search_dirs(self.start_dir.as_deref().unwrap_or(root), root)
+    }
+
+    /// For each candidate, the directory it applies in if it's available:
+    /// the nearest of `dirs` it's detected in, or the project root for tools
+    /// whose detection doesn't depend on project files.
+    fn probe(
+        &self,
+        config: &YamlConfig,
+        candidates: &[ToolRef],
+        dirs: &[PathBuf],
+    ) -> Vec<Option<Option<PathBuf>>> {
+        let root = dirs.last();
+        parallel::run_ordered(candidates, &[], self.jobs, |t| {
+            let ecosystem = config.ecosystems[t.ecosystem].detect.as_ref();
+            if dirs.is_empty() || !depends_on_dir(ecosystem, t.tool) {
+                return self
+                    .tool_available(t.tool, root.map(PathBuf::as_path))
+                    .then(|| root.cloned());
+            }
+            dirs.iter()
+                .find(|dir| {
+                    ecosystem.is_none_or(|d| detect::matches(d, Some(dir)))
+                        && self.tool_available(t.tool, Some(dir))
+                })
+                .map(|dir| Some(dir.clone()))
+        })
+    }
+
+    /// Applies the candidates that have a target and returns a report for
+    /// every candidate. `target` is where the others are reported.
+    fn apply_tools(
+        &self,
+        candidates: &[ToolRef],
+        targets: &[Option<Target>],
+        env: &ResolvedEnv,
+        target: Target,
+    ) -> Vec<ToolReport> {
+        let tools: Vec<(ToolRef, Target)> = candidates
+            .iter()
+            .zip(targets)
+            .filter_map(|(t, target)| target.map(|target| (*t, target)))
+            .collect();
+
+        // Tools only wait on the `after:` dependencies that are actually running
+        let deps: Vec<Vec<usize>> = tools
+            .iter()
+            .map(|(t, _)| {
+                t.tool
+                    .after
+                    .iter()
+                    .filter_map(|name| tools.iter().position(|(other, _)| other.tool.name == *name))
+                    .collect()
+            })
+            .collect();
+
+        let mut applied = parallel::run_ordered(&tools, &deps, self.jobs, |(t, target)| {
+            self.apply_tool(*t, env, *target)
+        })
+        .into_iter();
+
+        candidates
+            .iter()
+            .zip(targets)
+            .map(|(t, found)| {
+                if found.is_some() {
+                    applied.next().expect("one report per available tool")
+                } else {
+                    ToolReport {
+                        ecosystem: t.ecosystem.to_string(),
+                        tool: t.tool.name.clone(),
+                        status: ToolStatus::NotAvailable,
+                        project: target.member.map(Path::to_path_buf),
+                        toolchain: target.toolchain.cloned(),
+                        commands: vec![],
+                        messages: vec![format!("Tool {} not available, skipping", t.tool.name)],
                     }
-                    continue;
                 }
+            })
+            .collect()
+    }
+
+    /// The workspace member `root` stands for, if it isn't the main project.
+    fn member_dir<'a>(&self, root: Option<&'a Path>) -> Option<&'a Path> {
+        root.filter(|root| Some(*root) != self.project_root.as_deref())
+    }
+
+    /// Whether the tool's executable exists and the project matches its `detect:` block.
+    fn tool_available(&self, tool: &Tool, root: Option<&Path>) -> bool {
+        if tool
+            .detect
+            .as_ref()
+            .is_some_and(|d| !detect::matches(d, root))
+        {
+            return false;
+        }
+        // Paths like `node_modules/.bin/next` are relative to the project root
+        if Path::new(&tool.executable).components().count() > 1 {
+            return detect::find_executable(&tool.executable, root).is_some();
+        }
+        detect::find_executable(&tool.executable, None).is_some()
+    }
+
+    fn apply_tool(&self, tool_ref: ToolRef, env: &ResolvedEnv, target: Target) -> ToolReport {
+        let tool = tool_ref.tool;
+        let mut report = ToolReport {
+            ecosystem: tool_ref.ecosystem.to_string(),
+            tool: tool.name.clone(),
+            status: ToolStatus::Applied,
+            project: target.member.map(Path::to_path_buf),
+            toolchain: target.toolchain.cloned(),
+            commands: vec![],
+            messages: vec![],
+        };
+
+        let key = cache_key(tool, target);
+        let fingerprint = self
+            .cache
+            .as_ref()
+            .filter(|_| !tool.commands.is_empty())
+            .map(|cache| {
+                let fingerprint = self.fingerprint(tool, env, target);
+                let fresh = cache.lock().unwrap().is_fresh(&key, &fingerprint);
+                (fingerprint, fresh)
+            });
+        if let Some((_, true)) = fingerprint {
+            if !self.force {
+                report.status = ToolStatus::UpToDate;
+                report.commands = (0..tool.commands.len())
+                    .map(|index| CommandReport {
+                        id: tool.command_action_id(index),
+                        command: tool.commands[index].run.to_string(),
+                        outcome: CommandOutcome::NotRun,
+                    })
+                    .collect();
+                report.messages.push(format!(
+                    "Skipping {}: already applied with the same configuration",
+                    tool.name
+                ));
+                return report;
             }
+        }
 
-            for tool in &ecosystem.tools {
-                if tool.skip || self.ignore_tools.contains(&tool.name) {
+        for (index, cmd) in tool.commands.iter().enumerate() {
+            let id = tool.command_action_id(index);
+            let run = match (&cmd.system, tool.command_scope(index)) {
+                (Some(system), ActionScope::Global)
+                    if self.system && !self.paths.redirects_system_config(system) =>
+                {
+                    report.messages.push(format!(
+                        "Skipping {} for {}: its system-wide config can't be written under --root",
+                        id, tool.name
+                    ));
+                    report.commands.push(CommandReport {
+                        id,
+                        command: system.to_string(),
+                        outcome: CommandOutcome::NotRun,
+                    });
                     continue;
                 }
-
-                // Check if tool is available
-                if !self.check_command(&format!("command -v {} >/dev/null 2>&1", tool.executable)) {
-                    if self.verbose {
-                        println!("Tool {} not available, skipping", tool.name);
-                    }
+                (Some(system), ActionScope::Global) if self.system => system,
+                (None, ActionScope::Global) if self.system => {
+                    report.messages.push(format!(
+                        "Skipping {} for {}: it has no system-wide variant",
+                        id, tool.name
+                    ));
+                    report.commands.push(CommandReport {
+                        id,
+                        command: cmd.run.to_string(),
+                        outcome: CommandOutcome::NotRun,
+                    });
                     continue;
                 }
+                _ => &cmd.run,
+            };
+            if self.dry_run {
+                report.commands.push(CommandReport {
+                    id,
+                    command: run.to_string(),
+                    outcome: CommandOutcome::NotRun,
+                });
+                continue;
+            }
 
-                // Set environment variables
-                for (key, value) in &tool.env {
-                    if env::var(key).is_ok() {
-                        if self.verbose {
-                            println!(
-                                "Warning: Env var '{}' is already set; skipping override for tool '{}'",
-                                key, tool.name
-                            );
-                        }
+            // The most specific timeout wins: command, then tool, then global
+            let timeout = cmd
+                .timeout
+                .or(tool.timeout)
+                .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+                .unwrap_or(self.timeout);
+            // Project commands must not write into whatever directory we were started from
+            let current_dir = match tool.command_scope(index) {
+                ActionScope::Global => None,
+                ActionScope::Project => match target.root {
+                    Some(root) => Some(root.to_path_buf()),
+                    None => {
+                        report.messages.push(format!(
+                            "Warning: Skipping project command {} for {}: no project root found",
+                            id, tool.name
+                        ));
+                        report.commands.push(CommandReport {
+                            id,
+                            command: run.to_string(),
+                            outcome: CommandOutcome::NotRun,
+                        });
                         continue;
                     }
-                    env::set_var(key, value);
+                },
+            };
+            let options = ExecOptions {
+                timeout,
+                interactive: tool.interactive,
+                non_interactive_env: self
+                    .config
+                    .non_interactive_env
+                    .iter()
+                    .map(|(k, v)| (k.clone(), v.clone()))
+                    .collect(),
+                current_dir,
+                path_prefix: target.toolchain.map(|t| t.bin_dir.clone()),
+                extra_env: self.paths.child_env(),
+            };
+            let outcome = self.executor.run(run, env, &options);
+            match &outcome {
+                CommandOutcome::Success | CommandOutcome::NotRun => {}
+                CommandOutcome::Failed { .. } | CommandOutcome::SpawnFailed(_) => {
+                    report.messages.push(format!(
+                        "Warning: Command {} failed for {}: {}",
+                        id,
+                        tool.name,
+                        outcome.failure().unwrap_or_default()
+                    ))
                 }
+                CommandOutcome::TimedOut(timeout) => report.messages.push(format!(
+                    "Warning: Command {} timed out after {:?} for {}: {}",
+                    id, timeout, tool.name, cmd
+                )),
+                CommandOutcome::NeedsInteraction(prompt) => report.messages.push(format!(
+                    "Warning: Command {} required interaction for {}: {} (prompt: {})",
+                    id, tool.name, cmd, prompt
+                )),
+            }
+            report.commands.push(CommandReport {
+                id,
+                command: run.to_string(),
+                outcome,
+            });
+        }
 
-                // Run commands
-                for cmd in &tool.commands {
-                    if let Err(e) = self.run_command(cmd) {
-                        if self.verbose {
-                            println!("Warning: Command failed for {}: {}", tool.name, e);
-                        }
-                    }
-                }
+        // Failed commands are retried next time
+        let succeeded = report
+            .commands
+            .iter()
+            .all(|c| matches!(c.outcome, CommandOutcome::Success | CommandOutcome::NotRun));
+        if let (Some(cache), Some((fingerprint, _))) = (&self.cache, fingerprint) {
+            if succeeded && !self.dry_run {
+                cache.lock().unwrap().record(&key, &fingerprint);
             }
         }
+
+        report
     }
 
-    fn check_command(&self, cmd: &str) -> bool {
-        Command::new("sh")
-            .arg("-c")
-            .arg(cmd)
-            .output()
-            .map(|output| output.status.success())
-            .unwrap_or(false)
+    /// Hash of everything that decides what applying `tool` does: its env,
+    /// its commands and its executable. The project is part of the cache key.
+    fn fingerprint(&self, tool: &Tool, env: &ResolvedEnv, target: Target) -> String {
+        let mut actions = String::new();
+        for (key, entry) in env.iter().filter(|(_, e)| e.tool == tool.name) {
+            writeln!(actions, "env {}={}", key, entry.value).unwrap();
+        }
+        for cmd in &tool.commands {
+            writeln!(actions, "run {:?}", cmd).unwrap();
+        }
+        writeln!(actions, "system {}", self.system).unwrap();
+
+        let executable = match target.toolchain {
+            Some(toolchain) => detect::find_in_dir(&tool.executable, &toolchain.bin_dir),
+            None => detect::find_executable(&tool.executable, target.root),
+        };
+        cache::fingerprint(&actions, executable.as_deref())
+    }
+
+    fn print_env_status(&self, env: &ResolvedEnv) {
+        if !self.verbose {
+            return;
+        }
+
+        for (key, entry) in env.iter() {
+            let existing = entry.existing.as_deref().unwrap_or_default();
+            match entry.status {
+                EnvStatus::Set => {}
+                EnvStatus::AlreadySet => eprintln!(
+                    "Env var '{}' is already set to '{}' for tool '{}'",
+                    key, entry.value, entry.tool
+                ),
+                EnvStatus::Kept => eprintln!(
+                    "Warning: Env var '{}' is already set; skipping override for tool '{}'",
+                    key, entry.tool
+                ),
+                EnvStatus::Appended => eprintln!(
+                    "Appending '{}' to env var '{}' for tool '{}'",
+                    entry.value, key, entry.tool
+                ),
+                EnvStatus::Overridden => eprintln!(
+                    "Overriding env var '{}' ('{}' -> '{}') for tool '{}'",
+                    key, existing, entry.value, entry.tool
+                ),
+            }
+        }
+
+        for conflict in env.conflicts() {
+            eprintln!(
+                "Warning: Env var '{}' is set by both '{}' ({}) and '{}' ({}); keeping the value from '{}'",
+                conflict.key,
+                conflict.kept_tool,
+                conflict.kept_value,
+                conflict.dropped_tool,
+                conflict.dropped_value,
+                conflict.kept_tool
+            );
+        }
+    }
+
+    /// Tools that pass the filters and whose ecosystem is available in any of
+    /// `dirs`, in execution order.
+    fn candidate_tools<'a>(&self, config: &'a YamlConfig, dirs: &[PathBuf]) -> Vec<ToolRef<'a>> {
+        // A cycle is reported by `plan`
+        let ordered =
+            order::tool_order(config).unwrap_or_else(|_| order::declaration_order(config));
+
+        let mut ecosystem_available: HashMap<&str, bool> = HashMap::new();
+        let mut tools = Vec::new();
+
+        for tool_ref in ordered {
+            let ToolRef { ecosystem, tool } = tool_ref;
+
+            // Skip if ecosystems is not empty and doesn't contain this ecosystem
+            if !self.ecosystems.is_empty() && !self.ecosystems.iter().any(|e| e == ecosystem) {
+                continue;
+            }
+
+            if tool.skip || self.ignore_tools.contains(&tool.name) {
+                continue;
+            }
+
+            // Check if ecosystem is available, once per ecosystem
+            let available = *ecosystem_available.entry(ecosystem).or_insert_with(|| {
+                let eco = &config.ecosystems[ecosystem];
+                let available = eco.detect.as_ref().is_none_or(|d| detect_in(d, dirs))
+                    && eco
+                        .check_ecosystem
+                        .as_deref()
+                        .is_none_or(|cmd| executor::check_command(cmd, self.timeout));
+                if !available && self.verbose {
+                    eprintln!("Ecosystem {} not available, skipping", ecosystem);
+                }
+                available
+            });
+            if !available {
+                continue;
+            }
+
+            tools.push(tool_ref);
+        }
+
+        tools
     }
+}
 
-    fn run_command(&self, cmd: &str) -> Result<(), String> {
-        let output = Command::new("sh")
-            .arg("-c")
-            .arg(cmd)
-            .output()
-            .map_err(|e| e.to_string())?;
+/// What [`Runner::plan`] found: the resolved env and, for the main project,
+/// each workspace member and each toolchain, the tools that could apply.
+pub(crate) struct RunPlan {
+    env: ResolvedEnv,
+    groups: Vec<PlannedGroup>,
+    messages: Vec<String>,
+}
+
+struct PlannedGroup {
+    root: Option<PathBuf>,
+    toolchain: Option<Toolchain>,
+    /// The candidate tools in execution order
+    tools: Vec<PlannedTool>,
+}
 
-        if !output.status.success() {
-            return Err(String::from_utf8_lossy(&output.stderr).to_string());
+struct PlannedTool {
+    ecosystem: String,
+    tool: Tool,
+    /// The directory the tool applies in if it's available; see [`Runner::probe`]
+    found: Option<Option<PathBuf>>,
+}
+
+impl PlannedGroup {
+    fn new(
+        root: Option<PathBuf>,
+        toolchain: Option<Toolchain>,
+        candidates: &[ToolRef],
+        found: &[Option<Option<PathBuf>>],
+    ) -> Self {
+        PlannedGroup {
+            root,
+            toolchain,
+            tools: candidates
+                .iter()
+                .zip(found)
+                .map(|(t, found)| PlannedTool {
+                    ecosystem: t.ecosystem.to_string(),
+                    tool: t.tool.clone(),
+                    found: found.clone(),
+                })
+                .collect(),
         }
+    }
+}
 
-        Ok(())
+/// Cache key of a tool. Tools with project commands are cached per project,
+/// so switching projects doesn't invalidate the global ones.
+fn cache_key(tool: &Tool, target: Target) -> String {
+    let mut key = tool.name.clone();
+    if let Some(toolchain) = target.toolchain {
+        write!(key, " for {}", toolchain).unwrap();
+    }
+    if let Some(root) = target.root.filter(|_| has_project_commands(tool)) {
+        write!(key, " in {}", root.display()).unwrap();
     }
+    key
+}
+
+fn has_project_commands(tool: &Tool) -> bool {
+    (0..tool.commands.len()).any(|index| tool.command_scope(index) == ActionScope::Project)
+}
+
+/// Whether `detect` holds in any of `dirs`, or without a project if there are none.
+fn detect_in(detect: &Detect, dirs: &[PathBuf]) -> bool {
+    if dirs.is_empty() {
+        return detect::matches(detect, None);
+    }
+    dirs.iter().any(|dir| detect::matches(detect, Some(dir)))
+}
+
+/// Whether where the tool applies depends on the directory: its `detect:`
+/// blocks look at project files, or its executable is a relative path like
+/// `node_modules/.bin/next`.
+fn depends_on_dir(ecosystem: Option<&Detect>, tool: &Tool) -> bool {
+    [ecosystem, tool.detect.as_ref()]
+        .into_iter()
+        .flatten()
+        .any(detect::looks_at_files)
+        || Path::new(&tool.executable).components().count() > 1
+}
+
+/// Whether `toolchain` has its own copy of the tool with commands to run.
+/// The toolchain that is active on PATH is skipped; the main run covers it.
+fn in_toolchain(tool: &Tool, toolchain: &Toolchain) -> bool {
+    if tool.commands.is_empty() {
+        return false;
+    }
+    let Some(own) = detect::find_in_dir(&tool.executable, &toolchain.bin_dir) else {
+        return false;
+    };
+    let active = detect::find_executable(&tool.executable, None);
+    let same_dir = |a: &Path, b: &Path| match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    };
+    !active.is_some_and(
+        |active| matches!((active.parent(), own.parent()), (Some(a), Some(b)) if same_dir(a, b)),
+    )
+}
+
+fn default_jobs() -> usize {
+    thread::available_parallelism().map_or(1, |n| n.get())
 }
@@ -0,0 +1,147 @@
+use crate::yaml_config::{ActionKind, ActionScope, Profile, Tool, ToolCommand, YamlConfig};
+use indexmap::IndexMap;
+
+/// Which actions of the selected tools are applied. An action is a single
+/// env entry or command, identified by an ID such as `yarn.enableGlobalCache`.
+#[derive(Debug, Clone, Default)]
+pub struct Selection {
+    /// IDs of actions to leave out
+    pub skip_actions: Vec<String>,
+    /// If not empty, only these actions are applied, whatever their kind
+    pub only_actions: Vec<String>,
+    /// Kinds of actions to apply. If empty, everything except `behavior`
+    /// actions is applied.
+    pub kinds: Vec<ActionKind>,
+    /// If not empty, only these tools are applied
+    pub tools: Vec<String>,
+    /// Turns single actions on or off regardless of their kind
+    pub overrides: IndexMap<String, bool>,
+    /// If set, only actions with this scope are applied
+    pub scope: Option<ActionScope>,
+}
+
+impl Selection {
+    /// Takes the kinds, tools and overrides of `profile`.
+    pub fn with_profile(mut self, profile: &Profile) -> Self {
+        self.kinds = profile.kinds.clone();
+        self.tools = profile.tools.clone();
+        self.overrides = profile.overrides.clone();
+        self
+    }
+
+    pub fn allows(&self, id: &str, kind: Option<ActionKind>, scope: ActionScope) -> bool {
+        if self.skip_actions.iter().any(|skip| skip == id) {
+            return false;
+        }
+        if self.scope.is_some_and(|selected| selected != scope) {
+            return false;
+        }
+        if !self.only_actions.is_empty() {
+            // Naming an action explicitly opts into it
+            return self.only_actions.iter().any(|only| only == id);
+        }
+        if let Some(&enabled) = self.overrides.get(id) {
+            return enabled;
+        }
+        self.allows_kind(kind)
+    }
+
+    fn allows_kind(&self, kind: Option<ActionKind>) -> bool {
+        match kind {
+            _ if self.kinds.is_empty() => kind != Some(ActionKind::Behavior),
+            Some(kind) => self.kinds.contains(&kind),
+            None => false,
+        }
+    }
+
+    /// Returns a copy of `config` without the actions this selection leaves
+    /// out. Tools that lose all of their actions are dropped, as are tools
+    /// without actions when `only_actions`, `kinds` or `scope` is given.
+    pub fn apply(&self, config: &YamlConfig) -> YamlConfig {
+        let mut selected = config.clone();
+        for ecosystem in selected.ecosystems.values_mut() {
+            ecosystem.tools = ecosystem
+                .tools
+                .iter()
+                .filter_map(|tool| self.apply_to_tool(tool))
+                .collect();
+        }
+        selected
+    }
+
+    fn apply_to_tool(&self, tool: &Tool) -> Option<Tool> {
+        if !self.tools.is_empty() && !self.tools.contains(&tool.name) {
+            return None;
+        }
+        let had_actions = !tool.env.is_empty() || !tool.commands.is_empty();
+
+        let mut selected = tool.clone();
+        selected.env.retain(|key, _| {
+            self.allows(
+                &tool.env_action_id(key),
+                tool.env_kind(key),
+                tool.env_scope(key),
+            )
+        });
+        selected.commands = (0..tool.commands.len())
+            .filter(|&index| {
+                self.allows(
+                    &tool.command_action_id(index),
+                    tool.command_kind(index),
+                    tool.command_scope(index),
+                )
+            })
+            .map(|index| pinned_command(tool, index))
+            .collect();
+
+        let has_actions = !selected.env.is_empty() || !selected.commands.is_empty();
+        let keep_empty = !had_actions
+            && self.only_actions.is_empty()
+            && self.kinds.is_empty()
+            && self.scope.is_none();
+        (has_actions || keep_empty).then_some(selected)
+    }
+
+    /// Requested action IDs that don't exist in `config`.
+    pub fn unknown_ids(&self, config: &YamlConfig) -> Vec<String> {
+        let known: Vec<String> = config
+            .ecosystems
+            .values()
+            .flat_map(|ecosystem| &ecosystem.tools)
+            .flat_map(|tool| tool.action_ids())
+            .collect();
+        self.skip_actions
+            .iter()
+            .chain(&self.only_actions)
+            .filter(|id| !known.contains(id))
+            .cloned()
+            .collect()
+    }
+}
+
+/// Returns a copy of `config` with only the actions of the given scope.
+/// Tools without such actions are dropped.
+pub fn restrict_scope(config: &YamlConfig, scope: ActionScope) -> YamlConfig {
+    let mut restricted = config.clone();
+    for ecosystem in restricted.ecosystems.values_mut() {
+        ecosystem.tools.retain_mut(|tool| {
+            let original = tool.clone();
+            tool.env.retain(|key, _| original.env_scope(key) == scope);
+            tool.commands = (0..original.commands.len())
+                .filter(|&index| original.command_scope(index) == scope)
+                .map(|index| pinned_command(&original, index))
+                .collect();
+            !tool.env.is_empty() || !tool.commands.is_empty()
+        });
+    }
+    restricted
+}
+
+/// Clones a command with its ID pinned, so default IDs don't shift when
+/// earlier commands are dropped.
+fn pinned_command(tool: &Tool, index: usize) -> ToolCommand {
+    let mut command = tool.commands[index].clone();
+    let id = tool.command_action_id(index);
+    command.id = Some(id[tool.name.len() + 1..].to_string());
+    command
+}
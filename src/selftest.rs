@@ -0,0 +1,337 @@
+use crate::detect;
+use crate::executor::{self, ExecOptions};
+use crate::paths::Paths;
+use crate::report::CommandOutcome;
+use crate::resolved_env::ResolvedEnv;
+use crate::wrap::NagFilter;
+use crate::yaml_config::{CommandLine, Tool, YamlConfig};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+/// Env var with the prefix the installer should put the tool in
+pub const PREFIX_VAR: &str = "STOP_NAGGING_PREFIX";
+/// Env var with the name of the tool being installed
+pub const TOOL_VAR: &str = "STOP_NAGGING_TOOL";
+
+/// Vars that make update notifiers think they run in CI and stay quiet
+const CI_VARS: &[&str] = &[
+    "CI",
+    "CONTINUOUS_INTEGRATION",
+    "BUILD_NUMBER",
+    "RUN_ID",
+    "GITHUB_ACTIONS",
+];
+
+/// Time an installer may take; downloads are slower than config commands.
+pub const INSTALL_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// How the tools are installed and run.
+#[derive(Debug, Clone)]
+pub struct SelftestOptions {
+    /// Installer used instead of each tool's `install_for_testing`, e.g. a
+    /// script that copies a local stand-in into `$STOP_NAGGING_PREFIX/bin`
+    pub installer: Option<String>,
+    /// Timeout for the probes and the tool's commands
+    pub timeout: Duration,
+}
+
+impl Default for SelftestOptions {
+    fn default() -> Self {
+        SelftestOptions {
+            installer: None,
+            timeout: executor::DEFAULT_TIMEOUT,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verdict {
+    /// The probe nagged before applying the tool and didn't afterwards
+    Passed,
+    /// Nothing could be compared, e.g. the probe never nagged
+    Skipped(String),
+    Failed(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelftestResult {
+    pub tool: String,
+    pub verdict: Verdict,
+    /// Nag lines the probe printed before applying the tool
+    pub before: Vec<String>,
+    /// Nag lines the probe still printed afterwards
+    pub after: Vec<String>,
+}
+
+impl fmt::Display for SelftestResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.verdict {
+            Verdict::Passed => write!(f, "{}: passed", self.tool)?,
+            Verdict::Skipped(reason) => write!(f, "{}: skipped: {}", self.tool, reason)?,
+            Verdict::Failed(reason) => write!(f, "{}: FAILED: {}", self.tool, reason)?,
+        }
+        for line in &self.after {
+            write!(f, "\n    {}", line)?;
+        }
+        Ok(())
+    }
+}
+
+/// Tests every tool with a `nag_probe`, or only the named ones.
+pub fn run(config: &YamlConfig, only: &[String], options: &SelftestOptions) -> Vec<SelftestResult> {
+    config
+        .ecosystems
+        .values()
+        .flat_map(|eco| &eco.tools)
+        .filter(|tool| {
+            if only.is_empty() {
+                !tool.skip && tool.nag_probe.is_some()
+            } else {
+                only.contains(&tool.name)
+            }
+        })
+        .map(|tool| run_tool(tool, options))
+        .collect()
+}
+
+/// Installs `tool` into a temporary prefix with its own home, then checks
+/// that its `nag_probe` prints lines matching `nag_patterns` before the
+/// tool's env and commands are applied, and none afterwards.
+pub fn run_tool(tool: &Tool, options: &SelftestOptions) -> SelftestResult {
+    let mut result = SelftestResult {
+        tool: tool.name.clone(),
+        verdict: Verdict::Passed,
+        before: vec![],
+        after: vec![],
+    };
+    let Some(probe) = &tool.nag_probe else {
+        result.verdict = Verdict::Skipped("no nag_probe declared".to_string());
+        return result;
+    };
+    if tool.nag_patterns.is_empty() {
+        result.verdict = Verdict::Skipped("no nag_patterns declared".to_string());
+        return result;
+    }
+
+    let sandbox = match Sandbox::create(&tool.name) {
+        Ok(sandbox) => sandbox,
+        Err(e) => {
+            result.verdict = Verdict::Failed(format!("Failed to create the prefix: {}", e));
+            return result;
+        }
+    };
+    if let Err(reason) = sandbox.install(tool, options) {
+        result.verdict = reason;
+        return result;
+    }
+
+    let filter = NagFilter::new(&tool.nag_patterns);
+    result.before = match sandbox.probe(tool, probe, &ResolvedEnv::default(), options) {
+        Ok(output) => nag_lines(&filter, &output),
+        Err(reason) => {
+            result.verdict = Verdict::Failed(format!("Probe failed before applying: {}", reason));
+            return result;
+        }
+    };
+    if result.before.is_empty() {
+        result.verdict =
+            Verdict::Skipped("the probe printed no nag before applying the tool".to_string());
+        return result;
+    }
+
+    // Resolved against an env without the tool's vars, so every one of them is set
+    let base: HashMap<String, String> = std::env::vars()
+        .filter(|(key, _)| !tool.env.contains_key(key))
+        .collect();
+    let env = ResolvedEnv::resolve([tool], &base, true);
+    if let Err(reason) = sandbox.apply(tool, &env, options) {
+        result.verdict = Verdict::Failed(reason);
+        return result;
+    }
+
+    result.after = match sandbox.probe(tool, probe, &env, options) {
+        Ok(output) => nag_lines(&filter, &output),
+        Err(reason) => {
+            result.verdict = Verdict::Failed(format!("Probe failed after applying: {}", reason));
+            return result;
+        }
+    };
+    if !result.after.is_empty() {
+        result.verdict = Verdict::Failed("the probe still nags after applying".to_string());
+    }
+    result
+}
+
+fn nag_lines(filter: &NagFilter, output: &str) -> Vec<String> {
+    output
+        .lines()
+        .filter(|line| filter.is_nag(line.as_bytes()))
+        .map(str::to_string)
+        .collect()
+}
+
+fn describe(outcome: &CommandOutcome) -> String {
+    match outcome {
+        CommandOutcome::Success | CommandOutcome::NotRun => "succeeded".to_string(),
+        CommandOutcome::Failed { .. } | CommandOutcome::SpawnFailed(_) => {
+            format!("failed: {}", outcome.failure().unwrap_or_default())
+        }
+        CommandOutcome::TimedOut(timeout) => format!("timed out after {:?}", timeout),
+        CommandOutcome::NeedsInteraction(prompt) => format!("asked a question: {}", prompt),
+    }
+}
+
+/// A temporary prefix with `bin` and `home` directories, deleted on drop.
+struct Sandbox {
+    prefix: PathBuf,
+    paths: Paths,
+}
+
+impl Sandbox {
+    fn create(tool: &str) -> std::io::Result<Self> {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let prefix = std::env::temp_dir().join(format!(
+            "stop-nagging-selftest-{}-{}-{}",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed),
+            tool
+        ));
+        let home = prefix.join("home");
+        fs::create_dir_all(prefix.join("bin"))?;
+        fs::create_dir_all(&home)?;
+        Ok(Sandbox {
+            prefix,
+            paths: Paths {
+                home: Some(home),
+                root: None,
+            },
+        })
+    }
+
+    fn bin(&self) -> PathBuf {
+        self.prefix.join("bin")
+    }
+
+    fn exec_options(&self, timeout: Duration) -> ExecOptions {
+        let mut options = ExecOptions::new(timeout);
+        options.path_prefix = Some(self.bin());
+        options.extra_env = self.paths.child_env();
+        options
+    }
+
+    /// Runs the installer, or uses the tool on `PATH` if there is none.
+    fn install(&self, tool: &Tool, options: &SelftestOptions) -> Result<(), Verdict> {
+        let Some(installer) = options
+            .installer
+            .as_ref()
+            .or(tool.install_for_testing.as_ref())
+        else {
+            return match detect::find_executable(&tool.executable, None) {
+                Some(_) => Ok(()),
+                None => Err(Verdict::Skipped(format!(
+                    "{} is not installed and has no install_for_testing",
+                    tool.executable
+                ))),
+            };
+        };
+
+        let mut exec_options = self.exec_options(INSTALL_TIMEOUT);
+        let prefix = self.prefix.to_string_lossy().to_string();
+        exec_options.extra_env.extend([
+            (PREFIX_VAR.to_string(), prefix.clone()),
+            (TOOL_VAR.to_string(), tool.name.clone()),
+            // `npm install -g` installs into the prefix
+            ("NPM_CONFIG_PREFIX".to_string(), prefix),
+        ]);
+        let installer = CommandLine::Shell(installer.clone());
+        match executor::run_command_with(&installer, &ResolvedEnv::default(), &exec_options) {
+            CommandOutcome::Success | CommandOutcome::NotRun => {}
+            outcome => {
+                return Err(Verdict::Failed(format!(
+                    "Install command `{}` {}",
+                    installer,
+                    describe(&outcome)
+                )))
+            }
+        }
+        if detect::find_in_dir(&tool.executable, &self.bin()).is_none() {
+            return Err(Verdict::Failed(format!(
+                "The installer didn't put {} into {}",
+                tool.executable,
+                self.bin().display()
+            )));
+        }
+        Ok(())
+    }
+
+    /// Runs every command of the tool, whatever its kind.
+    fn apply(
+        &self,
+        tool: &Tool,
+        env: &ResolvedEnv,
+        options: &SelftestOptions,
+    ) -> Result<(), String> {
+        for (index, cmd) in tool.commands.iter().enumerate() {
+            let timeout = cmd
+                .timeout
+                .or(tool.timeout)
+                .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+                .unwrap_or(options.timeout);
+            match executor::run_command_with(&cmd.run, env, &self.exec_options(timeout)) {
+                CommandOutcome::Success | CommandOutcome::NotRun => {}
+                outcome => {
+                    return Err(format!(
+                        "Command {} {}",
+                        tool.command_action_id(index),
+                        describe(&outcome)
+                    ))
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Runs the probe and returns its stdout and stderr. The tool's own env
+    /// vars are only set if they are in `env`.
+    fn probe(
+        &self,
+        tool: &Tool,
+        probe: &CommandLine,
+        env: &ResolvedEnv,
+        options: &SelftestOptions,
+    ) -> Result<String, String> {
+        let mut exec_options = self.exec_options(options.timeout);
+        // The probe has to see the nag, and update notifiers stay quiet in CI
+        exec_options.non_interactive_env.clear();
+        let Some(mut command) =
+            executor::build_command(probe, &ResolvedEnv::default(), &exec_options)
+        else {
+            return Err("empty argv command".to_string());
+        };
+        for key in CI_VARS {
+            command.env_remove(key);
+        }
+        for key in tool.env.keys() {
+            command.env_remove(key);
+        }
+        command.envs(env.vars_to_set());
+
+        // Probes may exit non-zero, e.g. when offline; the output is what matters
+        let output = executor::output(command, options.timeout).map_err(|e| e.to_string())?;
+        Ok(format!(
+            "{}{}",
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        ))
+    }
+}
+
+impl Drop for Sandbox {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.prefix);
+    }
+}
@@ -0,0 +1,151 @@
+use crate::error::{Error, Result};
+use crate::export;
+use crate::report::{Report, ToolStatus};
+use crate::yaml_config::YamlConfig;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Second line of every shim, so that only our own files are listed or removed.
+const MARKER: &str = "# Generated by stop-nagging shims";
+
+/// Renders a shim that exports `exports` and then execs the real `executable`.
+/// The real one is searched on `PATH` after `shim_dir`, or anywhere else on
+/// `PATH` if the shim directory isn't on it. Files that are the shim itself
+/// are skipped, so a shim never runs itself.
+pub fn render_shim(executable: &str, exports: &str, shim_dir: &Path) -> String {
+    format!(
+        r#"#!/bin/sh
+{marker} for {executable}
+{exports}tool={tool}
+shim_dir={shim_dir}
+real=
+past=
+IFS=:
+for dir in $PATH; do
+  if [ "$dir" -ef "$shim_dir" ]; then
+    past=1
+  elif [ -n "$past" ] && [ -f "$dir/$tool" ] && [ -x "$dir/$tool" ] && ! [ "$dir/$tool" -ef "$0" ]; then
+    real="$dir/$tool"
+    break
+  fi
+done
+if [ -z "$real" ]; then
+  for dir in $PATH; do
+    if ! [ "$dir" -ef "$shim_dir" ] && [ -f "$dir/$tool" ] && [ -x "$dir/$tool" ] && ! [ "$dir/$tool" -ef "$0" ]; then
+      real="$dir/$tool"
+      break
+    fi
+  done
+fi
+unset IFS
+if [ -z "$real" ]; then
+  echo "stop-nagging shim: $tool not found on PATH" >&2
+  exit 127
+fi
+exec "$real" "$@"
+"#,
+        marker = MARKER,
+        executable = executable,
+        exports = exports,
+        tool = quote(executable),
+        shim_dir = quote(&shim_dir.to_string_lossy()),
+    )
+}
+
+/// What [`install`] did.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Installed {
+    pub written: Vec<PathBuf>,
+    /// Files in the way that aren't shims; they are left alone
+    pub skipped: Vec<PathBuf>,
+}
+
+/// Writes a shim for every available tool in `report` that has env vars.
+/// Existing files that aren't shims are left alone.
+pub fn install(dir: &Path, config: &YamlConfig, report: &Report) -> Result<Installed> {
+    fs::create_dir_all(dir).map_err(|e| Error::io(dir, e))?;
+    let dir = dir.canonicalize().map_err(|e| Error::io(dir, e))?;
+    let mut installed = Installed::default();
+
+    let available = report
+        .tools
+        .iter()
+        .filter(|t| t.status != ToolStatus::NotAvailable && t.project.is_none())
+        .filter(|t| t.toolchain.is_none());
+    for tool_report in available {
+        let Some(tool) = config
+            .ecosystems
+            .values()
+            .flat_map(|eco| &eco.tools)
+            .find(|tool| tool.name == tool_report.tool)
+        else {
+            continue;
+        };
+        // Shims are found by name on PATH, so project-relative executables can't have one
+        if Path::new(&tool.executable).components().count() > 1 {
+            continue;
+        }
+        let exports = export::render_tool_posix(&report.env, &tool.name);
+        if exports.is_empty() {
+            continue;
+        }
+
+        let path = dir.join(&tool.executable);
+        if path.exists() && !is_shim(&path) {
+            installed.skipped.push(path);
+            continue;
+        }
+        fs::write(&path, render_shim(&tool.executable, &exports, &dir))
+            .and_then(|_| make_executable(&path))
+            .map_err(|e| Error::io(&path, e))?;
+        installed.written.push(path);
+    }
+    Ok(installed)
+}
+
+/// The shims in `dir`, sorted by name.
+pub fn list(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut shims: Vec<PathBuf> = fs::read_dir(dir)
+        .map_err(|e| Error::io(dir, e))?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| is_shim(path))
+        .collect();
+    shims.sort();
+    Ok(shims)
+}
+
+/// Deletes the shims in `dir` and returns them. Other files are kept.
+pub fn remove(dir: &Path) -> Result<Vec<PathBuf>> {
+    let shims = list(dir)?;
+    for shim in &shims {
+        fs::remove_file(shim).map_err(|e| Error::io(shim, e))?;
+    }
+    Ok(shims)
+}
+
+fn is_shim(path: &Path) -> bool {
+    path.is_file()
+        && fs::read_to_string(path).is_ok_and(|contents| {
+            contents
+                .lines()
+                .nth(1)
+                .is_some_and(|l| l.starts_with(MARKER))
+        })
+}
+
+#[cfg(unix)]
+fn make_executable(path: &Path) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o755))
+}
+
+#[cfg(not(unix))]
+fn make_executable(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+fn quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
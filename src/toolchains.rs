@@ -0,0 +1,144 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A language version installed by a version manager, e.g. node 18.19.0 under nvm.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Toolchain {
+    pub manager: String,
+    pub language: String,
+    pub version: String,
+    /// Directory with the toolchain's executables
+    pub bin_dir: PathBuf,
+}
+
+impl fmt::Display for Toolchain {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} ({})", self.language, self.version, self.manager)
+    }
+}
+
+/// Install directories of a version manager: `<root>/<version>/<bin>`.
+struct Layout {
+    manager: &'static str,
+    language: &'static str,
+    /// Env var that overrides the default root
+    root_var: &'static str,
+    /// Default root relative to the home directory
+    default_root: &'static str,
+    /// Path appended to `root_var`'s value to get to the versions
+    versions: &'static str,
+    /// Path of the executables inside a version directory
+    bin: &'static str,
+}
+
+const LAYOUTS: &[Layout] = &[
+    Layout {
+        manager: "nvm",
+        language: "node",
+        root_var: "NVM_DIR",
+        default_root: ".nvm",
+        versions: "versions/node",
+        bin: "bin",
+    },
+    Layout {
+        manager: "fnm",
+        language: "node",
+        root_var: "FNM_DIR",
+        default_root: ".local/share/fnm",
+        versions: "node-versions",
+        bin: "installation/bin",
+    },
+    Layout {
+        manager: "pyenv",
+        language: "python",
+        root_var: "PYENV_ROOT",
+        default_root: ".pyenv",
+        versions: "versions",
+        bin: "bin",
+    },
+    Layout {
+        manager: "rbenv",
+        language: "ruby",
+        root_var: "RBENV_ROOT",
+        default_root: ".rbenv",
+        versions: "versions",
+        bin: "bin",
+    },
+    Layout {
+        manager: "rustup",
+        language: "rust",
+        root_var: "RUSTUP_HOME",
+        default_root: ".rustup",
+        versions: "toolchains",
+        bin: "bin",
+    },
+];
+
+/// Plugin managers that keep every language under `<root>/installs/<plugin>/<version>/bin`.
+const PLUGIN_MANAGERS: &[(&str, &str, &str)] = &[
+    ("asdf", "ASDF_DATA_DIR", ".asdf"),
+    ("mise", "MISE_DATA_DIR", ".local/share/mise"),
+];
+
+/// Lists the toolchains installed by nvm, fnm, pyenv, rbenv, rustup, asdf
+/// and mise under `home`, honoring their root env vars in `vars`.
+pub fn discover(home: &Path, vars: &HashMap<String, String>) -> Vec<Toolchain> {
+    let root = |var: &str, default: &str| {
+        vars.get(var)
+            .filter(|value| !value.is_empty())
+            .map(PathBuf::from)
+            .unwrap_or_else(|| home.join(default))
+    };
+
+    let mut toolchains = Vec::new();
+
+    for layout in LAYOUTS {
+        let versions = root(layout.root_var, layout.default_root).join(layout.versions);
+        for (version, dir) in subdirs(&versions) {
+            toolchains.push(Toolchain {
+                manager: layout.manager.to_string(),
+                language: layout.language.to_string(),
+                version,
+                bin_dir: dir.join(layout.bin),
+            });
+        }
+    }
+
+    for (manager, var, default) in PLUGIN_MANAGERS {
+        let installs = root(var, default).join("installs");
+        for (plugin, plugin_dir) in subdirs(&installs) {
+            for (version, dir) in subdirs(&plugin_dir) {
+                toolchains.push(Toolchain {
+                    manager: manager.to_string(),
+                    language: plugin.clone(),
+                    version,
+                    bin_dir: dir.join("bin"),
+                });
+            }
+        }
+    }
+
+    toolchains.retain(|toolchain| toolchain.bin_dir.is_dir());
+    toolchains
+}
+
+/// Named subdirectories in name order. Symlinks such as nvm's aliases are skipped.
+fn subdirs(dir: &Path) -> Vec<(String, PathBuf)> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return vec![];
+    };
+    let mut dirs: Vec<(String, PathBuf)> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_ok_and(|t| t.is_dir()))
+        .map(|entry| {
+            (
+                entry.file_name().to_string_lossy().to_string(),
+                entry.path(),
+            )
+        })
+        .collect();
+    dirs.sort();
+    dirs
+}
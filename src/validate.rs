@@ -0,0 +1,300 @@
+use crate::error::{Error, Result};
+use crate::order;
+use crate::yaml_config::{CommandLine, Tool, YamlConfig};
+use regex::Regex;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Issue {
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self.severity {
+            Severity::Warning => "Warning",
+            Severity::Error => "Error",
+        };
+        write!(f, "{}: {}", label, self.message)
+    }
+}
+
+/// Like [`validate`], but fails with [`Error::Invalid`] if any issue is an
+/// error. Otherwise returns the warnings.
+pub fn check(config: &YamlConfig) -> Result<Vec<Issue>> {
+    let issues = validate(config);
+    if issues.iter().any(|issue| issue.severity == Severity::Error) {
+        return Err(Error::Invalid { issues });
+    }
+    Ok(issues)
+}
+
+/// Checks a loaded configuration for problems that parsing alone doesn't catch.
+pub fn validate(config: &YamlConfig) -> Vec<Issue> {
+    let mut issues = Vec::new();
+    issues.extend(env_conflicts(config));
+    issues.extend(ordering_issues(config));
+    issues.extend(timeout_issues(config));
+    issues.extend(command_issues(config));
+    issues.extend(action_id_issues(config));
+    issues.extend(kind_issues(config));
+    issues.extend(profile_issues(config));
+    issues.extend(nag_pattern_issues(config));
+    issues
+}
+
+/// Reports `nag_patterns` that aren't valid regexes and probes without patterns.
+fn nag_pattern_issues(config: &YamlConfig) -> Vec<Issue> {
+    let mut issues = Vec::new();
+    for ecosystem in config.ecosystems.values() {
+        for tool in &ecosystem.tools {
+            if tool.nag_probe.is_some() && tool.nag_patterns.is_empty() {
+                issues.push(Issue {
+                    severity: Severity::Warning,
+                    message: format!(
+                        "Tool '{}' has a nag_probe but no nag_patterns to look for",
+                        tool.name
+                    ),
+                });
+            }
+            for pattern in &tool.nag_patterns {
+                if let Err(e) = Regex::new(pattern) {
+                    issues.push(Issue {
+                        severity: Severity::Error,
+                        message: format!(
+                            "Tool '{}' has an invalid nag pattern '{}': {}",
+                            tool.name, pattern, e
+                        ),
+                    });
+                }
+            }
+        }
+    }
+    issues
+}
+
+/// Reports profiles that name unknown tools or actions.
+fn profile_issues(config: &YamlConfig) -> Vec<Issue> {
+    let mut issues = Vec::new();
+    let tools: Vec<&Tool> = config
+        .ecosystems
+        .values()
+        .flat_map(|ecosystem| &ecosystem.tools)
+        .collect();
+    let action_ids: Vec<String> = tools.iter().flat_map(|tool| tool.action_ids()).collect();
+
+    for (name, profile) in &config.profiles {
+        for tool in &profile.tools {
+            if !tools.iter().any(|t| t.name == *tool) {
+                issues.push(Issue {
+                    severity: Severity::Warning,
+                    message: format!("Profile '{}' includes unknown tool '{}'", name, tool),
+                });
+            }
+        }
+        for id in profile.overrides.keys() {
+            if !action_ids.contains(id) {
+                issues.push(Issue {
+                    severity: Severity::Warning,
+                    message: format!("Profile '{}' overrides unknown action '{}'", name, id),
+                });
+            }
+        }
+    }
+
+    issues
+}
+
+/// Reports actions without a kind. `kind:` is required, since the default
+/// selection can't tell whether such an action changes behavior.
+fn kind_issues(config: &YamlConfig) -> Vec<Issue> {
+    let mut issues = Vec::new();
+
+    for ecosystem in config.ecosystems.values() {
+        for tool in &ecosystem.tools {
+            let env = tool
+                .env
+                .keys()
+                .filter(|key| tool.env_kind(key).is_none())
+                .map(|key| tool.env_action_id(key));
+            let commands = (0..tool.commands.len())
+                .filter(|&index| tool.command_kind(index).is_none())
+                .map(|index| tool.command_action_id(index));
+            for id in env.chain(commands) {
+                issues.push(Issue {
+                    severity: Severity::Error,
+                    message: format!("Action '{}' has no kind", id),
+                });
+            }
+        }
+    }
+
+    issues
+}
+
+/// Reports action IDs used by more than one env entry or command.
+fn action_id_issues(config: &YamlConfig) -> Vec<Issue> {
+    let mut issues = Vec::new();
+    let mut seen = HashSet::new();
+
+    for ecosystem in config.ecosystems.values() {
+        for tool in &ecosystem.tools {
+            for id in tool.action_ids() {
+                if !seen.insert(id.clone()) {
+                    issues.push(Issue {
+                        severity: Severity::Error,
+                        message: format!("Action ID '{}' is used more than once", id),
+                    });
+                }
+            }
+        }
+    }
+
+    issues
+}
+
+/// Reports empty argv commands and shell strings that don't need a shell.
+fn command_issues(config: &YamlConfig) -> Vec<Issue> {
+    let mut issues = Vec::new();
+
+    for ecosystem in config.ecosystems.values() {
+        for tool in &ecosystem.tools {
+            for cmd in &tool.commands {
+                match &cmd.run {
+                    CommandLine::Argv(argv) if argv.is_empty() => issues.push(Issue {
+                        severity: Severity::Error,
+                        message: format!("Tool '{}' has an empty argv command", tool.name),
+                    }),
+                    CommandLine::Shell(line) if is_plain_shell_command(line) => {
+                        issues.push(Issue {
+                            severity: Severity::Warning,
+                            message: format!(
+                                "Command '{}' of tool '{}' doesn't need a shell; use the argv form {:?}",
+                                line,
+                                tool.name,
+                                line.split_whitespace().collect::<Vec<_>>()
+                            ),
+                        })
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    issues
+}
+
+/// Whether splitting `line` on whitespace gives the same argv the shell would.
+fn is_plain_shell_command(line: &str) -> bool {
+    const BUILTINS: &[&str] = &[
+        "cd", "export", "set", "source", ".", "exit", "alias", "unset",
+    ];
+
+    let Some(program) = line.split_whitespace().next() else {
+        return false;
+    };
+    !program.contains('=')
+        && !BUILTINS.contains(&program)
+        && line
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == ' ' || "-_./:,@+=%".contains(c))
+}
+
+/// Reports timeouts that can't be turned into a duration.
+fn timeout_issues(config: &YamlConfig) -> Vec<Issue> {
+    let mut issues = Vec::new();
+    let invalid = |seconds: Option<f64>| seconds.is_some_and(|s| !s.is_finite() || s <= 0.0);
+
+    for ecosystem in config.ecosystems.values() {
+        for tool in &ecosystem.tools {
+            if invalid(tool.timeout) {
+                issues.push(Issue {
+                    severity: Severity::Error,
+                    message: format!("Tool '{}' has an invalid timeout", tool.name),
+                });
+            }
+            for cmd in &tool.commands {
+                if invalid(cmd.timeout) {
+                    issues.push(Issue {
+                        severity: Severity::Error,
+                        message: format!(
+                            "Command '{}' of tool '{}' has an invalid timeout",
+                            cmd, tool.name
+                        ),
+                    });
+                }
+            }
+        }
+    }
+
+    issues
+}
+
+/// Reports unknown tools in `after:` fields and ordering cycles.
+fn ordering_issues(config: &YamlConfig) -> Vec<Issue> {
+    let mut issues = Vec::new();
+    let tools = order::declaration_order(config);
+
+    for tool_ref in &tools {
+        for name in &tool_ref.tool.after {
+            if !tools.iter().any(|other| other.tool.name == *name) {
+                issues.push(Issue {
+                    severity: Severity::Warning,
+                    message: format!(
+                        "Tool '{}' should run after unknown tool '{}'",
+                        tool_ref.tool.name, name
+                    ),
+                });
+            }
+        }
+    }
+
+    if let Err(e) = order::tool_order(config) {
+        issues.push(Issue {
+            severity: Severity::Error,
+            message: e.to_string(),
+        });
+    }
+
+    issues
+}
+
+/// Reports env vars that two tools want set to different values.
+fn env_conflicts(config: &YamlConfig) -> Vec<Issue> {
+    let mut issues = Vec::new();
+    let mut seen: HashMap<&str, (&str, &str)> = HashMap::new();
+
+    for ecosystem in config.ecosystems.values() {
+        for tool in &ecosystem.tools {
+            for (key, setting) in &tool.env {
+                let value = setting.value.as_str();
+                match seen.get(key.as_str()) {
+                    Some((other_tool, other_value)) if *other_value != value => {
+                        issues.push(Issue {
+                            severity: Severity::Warning,
+                            message: format!(
+                                "Env var '{}' is set to '{}' by '{}' and to '{}' by '{}'",
+                                key, other_value, other_tool, value, tool.name
+                            ),
+                        });
+                    }
+                    Some(_) => {}
+                    None => {
+                        seen.insert(key, (&tool.name, value));
+                    }
+                }
+            }
+        }
+    }
+
+    issues
+}
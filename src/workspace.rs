@@ -0,0 +1,194 @@
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Finds the members of the workspace rooted at `root`, from
+/// `pnpm-workspace.yaml`, the `workspaces` field of `package.json`,
+/// `settings.gradle(.kts)` and the `[workspace]` table of `Cargo.toml`.
+///
+/// Members are existing directories other than `root`, sorted and without
+/// duplicates.
+pub fn members(root: &Path) -> Vec<PathBuf> {
+    let mut patterns = Vec::new();
+    patterns.extend(pnpm_patterns(root));
+    patterns.extend(package_json_patterns(root));
+    patterns.extend(cargo_patterns(root));
+
+    let (excluded, included): (Vec<String>, Vec<String>) =
+        patterns.into_iter().partition(|p| p.starts_with('!'));
+    let excluded: Vec<PathBuf> = excluded
+        .iter()
+        .flat_map(|pattern| expand(root, &pattern[1..]))
+        .collect();
+
+    let mut members: Vec<PathBuf> = included
+        .iter()
+        .flat_map(|pattern| expand(root, pattern))
+        .chain(gradle_members(root))
+        .filter(|dir| dir.is_dir() && dir != root && !excluded.contains(dir))
+        .collect();
+    members.sort();
+    members.dedup();
+    members
+}
+
+fn pnpm_patterns(root: &Path) -> Vec<String> {
+    let Ok(contents) = fs::read_to_string(root.join("pnpm-workspace.yaml")) else {
+        return vec![];
+    };
+    let yaml: serde_yaml::Value = serde_yaml::from_str(&contents).unwrap_or_default();
+    yaml.get("packages")
+        .and_then(|packages| packages.as_sequence())
+        .map(|packages| {
+            packages
+                .iter()
+                .filter_map(|p| p.as_str().map(str::to_string))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Accepts both `"workspaces": [...]` and `"workspaces": {"packages": [...]}`.
+fn package_json_patterns(root: &Path) -> Vec<String> {
+    let Ok(contents) = fs::read_to_string(root.join("package.json")) else {
+        return vec![];
+    };
+    let json: Value = serde_json::from_str(&contents).unwrap_or_default();
+    let workspaces = match &json["workspaces"] {
+        Value::Object(map) => map.get("packages").cloned().unwrap_or_default(),
+        other => other.clone(),
+    };
+    workspaces
+        .as_array()
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(|p| p.as_str().map(str::to_string))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn cargo_patterns(root: &Path) -> Vec<String> {
+    let Ok(contents) = fs::read_to_string(root.join("Cargo.toml")) else {
+        return vec![];
+    };
+    let Ok(manifest) = contents.parse::<toml::Table>() else {
+        return vec![];
+    };
+    let Some(workspace) = manifest.get("workspace") else {
+        return vec![];
+    };
+    let list = |key: &str| -> Vec<String> {
+        workspace
+            .get(key)
+            .and_then(|v| v.as_array())
+            .map(|items| {
+                items
+                    .iter()
+                    .filter_map(|p| p.as_str().map(str::to_string))
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+    let excluded = list("exclude").into_iter().map(|p| format!("!{}", p));
+    list("members").into_iter().chain(excluded).collect()
+}
+
+/// Reads `include ':app', ':libs:core'` and `include("app")` lines. Gradle
+/// maps `:libs:core` to the directory `libs/core`.
+fn gradle_members(root: &Path) -> Vec<PathBuf> {
+    let contents = ["settings.gradle", "settings.gradle.kts"]
+        .iter()
+        .find_map(|name| fs::read_to_string(root.join(name)).ok())
+        .unwrap_or_default();
+
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| line.starts_with("include"))
+        .flat_map(|line| {
+            line.split(['\'', '"'])
+                .skip(1)
+                .step_by(2)
+                .map(|project| {
+                    project
+                        .trim_start_matches(':')
+                        .split(':')
+                        .fold(root.to_path_buf(), |dir, part| dir.join(part))
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// Expands a workspace pattern such as `packages/*` or `apps/**` into
+/// directories. `*` matches within one path segment, `**` any number of
+/// directories.
+fn expand(root: &Path, pattern: &str) -> Vec<PathBuf> {
+    let segments: Vec<&str> = pattern
+        .trim_start_matches("./")
+        .trim_end_matches('/')
+        .split('/')
+        .filter(|s| !s.is_empty())
+        .collect();
+    let mut found = Vec::new();
+    expand_segments(root, &segments, &mut found);
+    found
+}
+
+fn expand_segments(dir: &Path, segments: &[&str], found: &mut Vec<PathBuf>) {
+    let Some((first, rest)) = segments.split_first() else {
+        found.push(dir.to_path_buf());
+        return;
+    };
+
+    if *first == "**" {
+        expand_segments(dir, rest, found);
+        for child in subdirs(dir) {
+            expand_segments(&child, segments, found);
+        }
+    } else if first.contains('*') {
+        for child in subdirs(dir) {
+            let name = child.file_name().unwrap_or_default().to_string_lossy();
+            if wildcard_match(first, &name) {
+                expand_segments(&child, rest, found);
+            }
+        }
+    } else {
+        expand_segments(&dir.join(first), rest, found);
+    }
+}
+
+/// Subdirectories, skipping hidden ones and `node_modules`.
+fn subdirs(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return vec![];
+    };
+    let mut dirs: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            path.is_dir() && !name.starts_with('.') && name != "node_modules"
+        })
+        .collect();
+    dirs.sort();
+    dirs
+}
+
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    match pattern.split_once('*') {
+        None => pattern == name,
+        Some((prefix, rest)) => {
+            let Some(after) = name.strip_prefix(prefix) else {
+                return false;
+            };
+            after
+                .char_indices()
+                .map(|(i, _)| i)
+                .chain([after.len()])
+                .any(|i| wildcard_match(rest, &after[i..]))
+        }
+    }
+}
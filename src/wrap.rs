@@ -0,0 +1,225 @@
+use crate::error::{Error, Result};
+use crate::yaml_config::YamlConfig;
+use regex::Regex;
+use std::io::{self, IsTerminal, Read, Write};
+use std::path::Path;
+use std::process::{Command, ExitStatus, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+
+/// How long [`NagFilter::filter`] holds back a line without a newline before
+/// passing it on, e.g. a prompt that waits for an answer
+pub const PARTIAL_LINE_IDLE: Duration = Duration::from_millis(500);
+/// Longest line [`NagFilter::filter`] holds back; longer output without a
+/// newline is a progress bar or binary data, not a nag
+pub const MAX_PARTIAL_LINE: usize = 64 * 1024;
+
+/// Drops output lines that match a tool's `nag_patterns`.
+#[derive(Debug, Clone, Default)]
+pub struct NagFilter {
+    patterns: Vec<Regex>,
+}
+
+impl NagFilter {
+    /// Compiles `patterns`; invalid ones are skipped (`validate` reports them).
+    pub fn new<S: AsRef<str>>(patterns: &[S]) -> Self {
+        NagFilter {
+            patterns: patterns
+                .iter()
+                .filter_map(|p| Regex::new(p.as_ref()).ok())
+                .collect(),
+        }
+    }
+
+    /// The filter for the tool whose executable is `program`, e.g. `npm` or
+    /// `/usr/bin/npm`. Unknown programs get a filter that keeps every line.
+    pub fn for_program(config: &YamlConfig, program: &str) -> Self {
+        let name = Path::new(program)
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let patterns: Vec<&String> = config
+            .ecosystems
+            .values()
+            .flat_map(|eco| &eco.tools)
+            .filter(|tool| {
+                Path::new(&tool.executable)
+                    .file_name()
+                    .is_some_and(|exe| exe.to_string_lossy() == name)
+            })
+            .flat_map(|tool| &tool.nag_patterns)
+            .collect();
+        NagFilter::new(&patterns)
+    }
+
+    /// Whether `line` is a nag. Colors are ignored when matching.
+    pub fn is_nag(&self, line: &[u8]) -> bool {
+        if self.patterns.is_empty() {
+            return false;
+        }
+        let text = strip_ansi(&String::from_utf8_lossy(line));
+        let text = text.trim_end_matches(['\n', '\r']);
+        self.patterns.iter().any(|pattern| pattern.is_match(text))
+    }
+
+    /// Copies `input` to `output` line by line without the nag lines. A line
+    /// is held back until its newline arrives, so a nag written in several
+    /// pieces is still dropped. Output that stays without a newline, like a
+    /// prompt or a progress bar, is passed on unfiltered after
+    /// [`PARTIAL_LINE_IDLE`] or once it reaches [`MAX_PARTIAL_LINE`] bytes.
+    pub fn filter<R: Read + Send, W: Write>(&self, mut input: R, mut output: W) -> Result<()> {
+        let (sender, chunks) = mpsc::channel();
+        thread::scope(|scope| {
+            scope.spawn(move || {
+                let mut buf = [0u8; 8192];
+                loop {
+                    let chunk = match input.read(&mut buf) {
+                        Ok(0) => break,
+                        Ok(n) => Ok(buf[..n].to_vec()),
+                        Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                        Err(e) => Err(e),
+                    };
+                    let failed = chunk.is_err();
+                    // The receiver is gone once writing failed; stop reading
+                    // so the command gets SIGPIPE
+                    if sender.send(chunk).is_err() || failed {
+                        break;
+                    }
+                }
+            });
+            self.filter_chunks(chunks, &mut output)
+        })
+    }
+
+    fn filter_chunks<W: Write>(
+        &self,
+        chunks: Receiver<io::Result<Vec<u8>>>,
+        output: &mut W,
+    ) -> Result<()> {
+        let mut line = Vec::new();
+        // The start of the current line was passed on already, so the rest is too
+        let mut partial_sent = false;
+        loop {
+            let chunk = if line.is_empty() {
+                chunks.recv().ok()
+            } else {
+                match chunks.recv_timeout(PARTIAL_LINE_IDLE) {
+                    Ok(chunk) => Some(chunk),
+                    Err(RecvTimeoutError::Timeout) => {
+                        output.write_all(&line)?;
+                        output.flush()?;
+                        line.clear();
+                        partial_sent = true;
+                        continue;
+                    }
+                    Err(RecvTimeoutError::Disconnected) => None,
+                }
+            };
+            let Some(chunk) = chunk else {
+                break;
+            };
+            let chunk = chunk?;
+            let mut rest = &chunk[..];
+            while let Some(pos) = rest.iter().position(|&b| b == b'\n') {
+                line.extend_from_slice(&rest[..=pos]);
+                if partial_sent || !self.is_nag(&line) {
+                    output.write_all(&line)?;
+                }
+                line.clear();
+                partial_sent = false;
+                rest = &rest[pos + 1..];
+            }
+            line.extend_from_slice(rest);
+            if line.len() >= MAX_PARTIAL_LINE {
+                output.write_all(&line)?;
+                line.clear();
+                partial_sent = true;
+            }
+            output.flush()?;
+        }
+        // The last line has no newline but is complete
+        if partial_sent || !self.is_nag(&line) {
+            output.write_all(&line)?;
+        }
+        output.flush()?;
+        Ok(())
+    }
+}
+
+/// Runs `argv` with its stdout and stderr filtered and returns its exit code.
+/// Stdin is inherited. When stop-nagging writes to a terminal, the child is
+/// asked to keep its colors even though its output is a pipe.
+pub fn run(argv: &[String], filter: &NagFilter) -> Result<i32> {
+    let (program, args) = argv.split_first().ok_or_else(|| Error::Spawn {
+        command: String::new(),
+        source: io::Error::new(io::ErrorKind::InvalidInput, "no command given"),
+    })?;
+    let mut command = Command::new(program);
+    command
+        .args(args)
+        .stdin(Stdio::inherit())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    if io::stdout().is_terminal() || io::stderr().is_terminal() {
+        for key in ["FORCE_COLOR", "CLICOLOR_FORCE"] {
+            if std::env::var_os(key).is_none() {
+                command.env(key, "1");
+            }
+        }
+    }
+
+    let mut child = command.spawn().map_err(|source| Error::Spawn {
+        command: argv.join(" "),
+        source,
+    })?;
+    let (Some(stdout), Some(stderr)) = (child.stdout.take(), child.stderr.take()) else {
+        return Err(io::Error::other("the command's output isn't piped").into());
+    };
+    let status = thread::scope(|scope| {
+        let out = scope.spawn(|| filter.filter(stdout, io::stdout()));
+        let err = scope.spawn(|| filter.filter(stderr, io::stderr()));
+        // A closed stdout on our side (e.g. `| head`) must not hide the exit code
+        let _ = out.join();
+        let _ = err.join();
+        child.wait()
+    })?;
+    Ok(exit_code(status))
+}
+
+#[cfg(unix)]
+fn exit_code(status: ExitStatus) -> i32 {
+    use std::os::unix::process::ExitStatusExt;
+    status
+        .code()
+        .or_else(|| status.signal().map(|signal| 128 + signal))
+        .unwrap_or(1)
+}
+
+#[cfg(not(unix))]
+fn exit_code(status: ExitStatus) -> i32 {
+    status.code().unwrap_or(1)
+}
+
+/// Removes ANSI escape sequences such as colors.
+fn strip_ansi(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            out.push(c);
+            continue;
+        }
+        if chars.next_if_eq(&'[').is_some() {
+            // CSI: parameters and intermediates, then one final byte
+            for c in chars.by_ref() {
+                if ('@'..='~').contains(&c) {
+                    break;
+                }
+            }
+        } else {
+            chars.next();
+        }
+    }
+    out
+}
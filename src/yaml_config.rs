@@ -1,40 +1,590 @@
+use crate::error::Error;
+use indexmap::IndexMap;
+use serde::de::value::{MapAccessDeserializer, SeqAccessDeserializer};
+use serde::de::{self, MapAccess, SeqAccess, Visitor};
 use serde::Deserialize;
-use std::collections::HashMap;
+use std::fmt;
 use std::fs;
 use std::path::Path;
+use std::str::FromStr;
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Clone)]
 pub struct YamlConfig {
-    pub ecosystems: HashMap<String, Ecosystem>,
+    pub ecosystems: IndexMap<String, Ecosystem>,
+    /// Extra env vars given to every non-interactive tool command, but never
+    /// exported to the user's shell
+    #[serde(default = "default_non_interactive_env")]
+    pub non_interactive_env: IndexMap<String, String>,
+    /// Named sets of actions, e.g. `ci` or `dev`
+    #[serde(default)]
+    pub profiles: IndexMap<String, Profile>,
+}
+
+impl Default for YamlConfig {
+    fn default() -> Self {
+        YamlConfig {
+            ecosystems: IndexMap::new(),
+            non_interactive_env: default_non_interactive_env(),
+            profiles: IndexMap::new(),
+        }
+    }
+}
+
+pub fn default_non_interactive_env() -> IndexMap<String, String> {
+    [
+        ("CI", "1"),
+        ("DEBIAN_FRONTEND", "noninteractive"),
+        ("NO_COLOR", "1"),
+    ]
+    .into_iter()
+    .map(|(key, value)| (key.to_string(), value.to_string()))
+    .collect()
 }
 
 impl YamlConfig {
-    pub fn from_default() -> Result<Self, Box<dyn std::error::Error>> {
+    /// The configuration built into the binary, from `tools.yaml`.
+    pub fn from_default() -> crate::Result<Self> {
         let config_str = include_str!("../tools.yaml");
-        let config: YamlConfig = serde_yaml::from_str(config_str)?;
-        Ok(config)
+        serde_yaml::from_str(config_str).map_err(|e| Error::parse(None, e))
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> crate::Result<Self> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path).map_err(|e| Error::io(path, e))?;
+        serde_yaml::from_str(&contents).map_err(|e| Error::parse(Some(path), e))
     }
 
-    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn std::error::Error>> {
-        let contents = fs::read_to_string(path)?;
-        let config: YamlConfig = serde_yaml::from_str(&contents)?;
-        Ok(config)
+    /// Parses YAML that didn't come from a file.
+    pub fn from_yaml(yaml: &str) -> crate::Result<Self> {
+        serde_yaml::from_str(yaml).map_err(|e| Error::parse(None, e))
     }
+
+    /// Layers `other` on top of this configuration. Tools replace the tool of
+    /// the same name in the same ecosystem and are appended otherwise; an
+    /// ecosystem's `detect:` and `check_ecosystem` are replaced if `other`
+    /// sets them. Profiles and non-interactive env vars replace those with
+    /// the same name.
+    pub fn merge(&mut self, other: YamlConfig) {
+        for (name, ecosystem) in other.ecosystems {
+            let Some(existing) = self.ecosystems.get_mut(&name) else {
+                self.ecosystems.insert(name, ecosystem);
+                continue;
+            };
+            if ecosystem.detect.is_some() {
+                existing.detect = ecosystem.detect;
+            }
+            if ecosystem.check_ecosystem.is_some() {
+                existing.check_ecosystem = ecosystem.check_ecosystem;
+            }
+            for tool in ecosystem.tools {
+                match existing.tools.iter_mut().find(|t| t.name == tool.name) {
+                    Some(slot) => *slot = tool,
+                    None => existing.tools.push(tool),
+                }
+            }
+        }
+        self.non_interactive_env.extend(other.non_interactive_env);
+        self.profiles.extend(other.profiles);
+    }
+}
+
+/// A named selection of actions.
+#[derive(Debug, Deserialize, Clone, Default, PartialEq, Eq)]
+pub struct Profile {
+    /// Kinds of actions to apply; empty means everything but `behavior`
+    #[serde(default)]
+    pub kinds: Vec<ActionKind>,
+    /// If not empty, only these tools are applied
+    #[serde(default)]
+    pub tools: Vec<String>,
+    /// Turns single actions on or off by ID, regardless of their kind
+    #[serde(default)]
+    pub overrides: IndexMap<String, bool>,
+}
+
+/// Conditions that tell whether a project uses a tool or ecosystem. Any one
+/// matching condition is enough. Paths are relative to the project root.
+#[derive(Debug, Deserialize, Clone, Default, PartialEq, Eq)]
+pub struct Detect {
+    /// Files such as `package.json` or `poetry.lock`
+    #[serde(default)]
+    pub files: Vec<String>,
+    /// Dotted paths that must exist in a JSON file, keyed by file,
+    /// e.g. `package.json: ["dependencies.next"]`
+    #[serde(default)]
+    pub json_path: IndexMap<String, Vec<String>>,
+    /// Executables, either relative to the project root
+    /// (`node_modules/.bin/next`) or looked up on `PATH`
+    #[serde(default)]
+    pub executables: Vec<String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Clone, Default)]
 pub struct Ecosystem {
     pub check_ecosystem: Option<String>,
+    /// Only use the ecosystem in projects that match
+    pub detect: Option<Detect>,
     pub tools: Vec<Tool>,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Clone, Default)]
 pub struct Tool {
     pub name: String,
     pub executable: String,
-    pub env: HashMap<String, String>,
-    pub commands: Vec<String>,
+    #[serde(default)]
+    pub env: IndexMap<String, EnvSetting>,
+    #[serde(default)]
+    pub commands: Vec<ToolCommand>,
+    #[serde(default)]
     pub skip: bool,
-    #[allow(dead_code)]
+    /// Shell command that installs the tool for `selftest`. It runs with
+    /// `STOP_NAGGING_PREFIX` set and should put the tool in its `bin`
     pub install_for_testing: Option<String>,
+    /// Names of tools that must be applied before this one
+    #[serde(default)]
+    pub after: Vec<String>,
+    /// Timeout in seconds for each of this tool's commands
+    pub timeout: Option<f64>,
+    /// Let commands use the terminal: stdin is inherited and no
+    /// non-interactive env vars are injected
+    #[serde(default)]
+    pub interactive: bool,
+    /// Default kind for actions that don't declare their own
+    pub kind: Option<ActionKind>,
+    /// Default scope for actions that don't declare their own
+    pub scope: Option<ActionScope>,
+    /// Only apply the tool in projects that match
+    pub detect: Option<Detect>,
+    /// Regexes for output lines that `wrap` drops, for banners that can't
+    /// be turned off
+    #[serde(default)]
+    pub nag_patterns: Vec<String>,
+    /// Command that prints the tool's nag, which `selftest` runs before and
+    /// after applying the tool
+    pub nag_probe: Option<CommandLine>,
+}
+
+impl Tool {
+    /// Stable ID of the env entry `key`, e.g. `yarn.YARN_IGNORE_PATH`.
+    pub fn env_action_id(&self, key: &str) -> String {
+        format!("{}.{}", self.name, key)
+    }
+
+    /// Stable ID of the command at `index`, e.g. `yarn.enableGlobalCache`.
+    /// Commands without an explicit `id` are numbered from 1.
+    pub fn command_action_id(&self, index: usize) -> String {
+        match &self.commands[index].id {
+            Some(id) => format!("{}.{}", self.name, id),
+            None => format!("{}.command-{}", self.name, index + 1),
+        }
+    }
+
+    /// Kind of the env entry `key`, falling back to the tool's kind.
+    pub fn env_kind(&self, key: &str) -> Option<ActionKind> {
+        self.env
+            .get(key)
+            .and_then(|setting| setting.kind)
+            .or(self.kind)
+    }
+
+    /// Kind of the command at `index`, falling back to the tool's kind.
+    pub fn command_kind(&self, index: usize) -> Option<ActionKind> {
+        self.commands[index].kind.or(self.kind)
+    }
+
+    /// Scope of the env entry `key`, falling back to the tool's scope.
+    pub fn env_scope(&self, key: &str) -> ActionScope {
+        self.env
+            .get(key)
+            .and_then(|setting| setting.scope)
+            .or(self.scope)
+            .unwrap_or_default()
+    }
+
+    /// Scope of the command at `index`, falling back to the tool's scope.
+    pub fn command_scope(&self, index: usize) -> ActionScope {
+        self.commands[index]
+            .scope
+            .or(self.scope)
+            .unwrap_or_default()
+    }
+
+    /// IDs of every env entry and command of this tool.
+    pub fn action_ids(&self) -> Vec<String> {
+        let env = self.env.keys().map(|key| self.env_action_id(key));
+        let commands = (0..self.commands.len()).map(|index| self.command_action_id(index));
+        env.chain(commands).collect()
+    }
+}
+
+/// What an action does, so that users can choose by category.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum ActionKind {
+    /// Silences update or upgrade notices and similar nags
+    UpdateNag,
+    /// Opts out of usage reporting
+    Telemetry,
+    /// Suppresses interactive prompts, progress bars and first-run messages
+    Prompt,
+    /// Changes how the tool behaves, e.g. caching; opt-in
+    Behavior,
+}
+
+impl ActionKind {
+    pub const ALL: [ActionKind; 4] = [
+        ActionKind::UpdateNag,
+        ActionKind::Telemetry,
+        ActionKind::Prompt,
+        ActionKind::Behavior,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ActionKind::UpdateNag => "update_nag",
+            ActionKind::Telemetry => "telemetry",
+            ActionKind::Prompt => "prompt",
+            ActionKind::Behavior => "behavior",
+        }
+    }
+}
+
+impl fmt::Display for ActionKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for ActionKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ActionKind::ALL
+            .into_iter()
+            .find(|kind| kind.as_str() == s)
+            .ok_or_else(|| {
+                let names: Vec<&str> = ActionKind::ALL.iter().map(|k| k.as_str()).collect();
+                format!("unknown kind '{}', expected one of {}", s, names.join(", "))
+            })
+    }
+}
+
+/// Where an action has its effect.
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ActionScope {
+    /// Changes user-wide configuration
+    #[default]
+    Global,
+    /// Changes files of the current project; commands run in the project root
+    Project,
+}
+
+/// What to do when an env var is already set before stop-nagging runs.
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum EnvPolicy {
+    /// Leave the existing value alone
+    #[default]
+    Keep,
+    /// Always replace the existing value
+    Override,
+    /// Replace the existing value only when it leaves the nag enabled,
+    /// e.g. `NEXT_TELEMETRY_DISABLED=0`
+    OverrideIfEnabled,
+}
+
+/// How an env entry is combined with an existing value.
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum EnvMode {
+    /// Set the variable to the value, subject to the policy
+    #[default]
+    Set,
+    /// Add the value as a token to a separated list such as `GRADLE_OPTS`,
+    /// keeping whatever is already there
+    AppendToken,
+}
+
+/// Separator between tokens for `append_token` entries.
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum EnvSeparator {
+    #[default]
+    Space,
+    /// The platform's `PATH` separator
+    Path,
+}
+
+impl EnvSeparator {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EnvSeparator::Space => " ",
+            EnvSeparator::Path if cfg!(windows) => ";",
+            EnvSeparator::Path => ":",
+        }
+    }
+}
+
+/// A single env entry of a tool. Accepts either a plain value or a map with
+/// `value` and optional `policy`, `mode`, `separator`, `kind` and `scope`.
+/// Plain values may be any scalar, so `FOO: 1` and `BAR: true` work.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EnvSetting {
+    pub value: String,
+    pub policy: EnvPolicy,
+    pub mode: EnvMode,
+    pub separator: EnvSeparator,
+    pub kind: Option<ActionKind>,
+    pub scope: Option<ActionScope>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DetailedEnvSetting {
+    #[serde(deserialize_with = "scalar_string")]
+    value: String,
+    #[serde(default)]
+    policy: EnvPolicy,
+    #[serde(default)]
+    mode: EnvMode,
+    #[serde(default)]
+    separator: EnvSeparator,
+    kind: Option<ActionKind>,
+    scope: Option<ActionScope>,
+}
+
+impl<'de> Deserialize<'de> for EnvSetting {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct EnvSettingVisitor;
+
+        impl<'de> Visitor<'de> for EnvSettingVisitor {
+            type Value = EnvSetting;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a value or a map with `value`")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<EnvSetting, A::Error> {
+                let detailed = DetailedEnvSetting::deserialize(MapAccessDeserializer::new(map))?;
+                Ok(EnvSetting {
+                    value: detailed.value,
+                    policy: detailed.policy,
+                    mode: detailed.mode,
+                    separator: detailed.separator,
+                    kind: detailed.kind,
+                    scope: detailed.scope,
+                })
+            }
+
+            fn visit_str<E: de::Error>(self, value: &str) -> Result<EnvSetting, E> {
+                Ok(EnvSetting::from(value))
+            }
+
+            fn visit_bool<E: de::Error>(self, value: bool) -> Result<EnvSetting, E> {
+                Ok(EnvSetting::from(value.to_string()))
+            }
+
+            fn visit_i64<E: de::Error>(self, value: i64) -> Result<EnvSetting, E> {
+                Ok(EnvSetting::from(value.to_string()))
+            }
+
+            fn visit_u64<E: de::Error>(self, value: u64) -> Result<EnvSetting, E> {
+                Ok(EnvSetting::from(value.to_string()))
+            }
+
+            fn visit_f64<E: de::Error>(self, value: f64) -> Result<EnvSetting, E> {
+                Ok(EnvSetting::from(value.to_string()))
+            }
+        }
+
+        deserializer.deserialize_any(EnvSettingVisitor)
+    }
+}
+
+/// Deserializes any YAML scalar as a string, e.g. `1` as `"1"`.
+fn scalar_string<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    // serde_yaml hands out the text of a plain scalar when asked for a string
+    String::deserialize(deserializer)
+}
+
+impl From<&str> for EnvSetting {
+    fn from(value: &str) -> Self {
+        EnvSetting::from(value.to_string())
+    }
+}
+
+impl From<String> for EnvSetting {
+    fn from(value: String) -> Self {
+        EnvSetting {
+            value,
+            ..Default::default()
+        }
+    }
+}
+
+/// A command line, either a string run through `sh -c` or an argv list
+/// executed directly without a shell.
+#[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum CommandLine {
+    Shell(String),
+    Argv(Vec<String>),
+}
+
+impl Default for CommandLine {
+    fn default() -> Self {
+        CommandLine::Shell(String::new())
+    }
+}
+
+impl From<&str> for CommandLine {
+    fn from(line: &str) -> Self {
+        CommandLine::Shell(line.to_string())
+    }
+}
+
+impl From<String> for CommandLine {
+    fn from(line: String) -> Self {
+        CommandLine::Shell(line)
+    }
+}
+
+impl From<Vec<String>> for CommandLine {
+    fn from(argv: Vec<String>) -> Self {
+        CommandLine::Argv(argv)
+    }
+}
+
+impl PartialEq<&str> for CommandLine {
+    fn eq(&self, other: &&str) -> bool {
+        matches!(self, CommandLine::Shell(cmd) if cmd == other)
+    }
+}
+
+impl fmt::Display for CommandLine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CommandLine::Shell(cmd) => f.write_str(cmd),
+            CommandLine::Argv(argv) => {
+                let quoted: Vec<String> = argv.iter().map(|arg| quote_arg(arg)).collect();
+                f.write_str(&quoted.join(" "))
+            }
+        }
+    }
+}
+
+fn quote_arg(arg: &str) -> String {
+    let plain = !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./:,@+=%".contains(c));
+    if plain {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', r"'\''"))
+    }
+}
+
+/// A command run for a tool. Accepts a shell string, an argv list, or a map
+/// with `run` (either form) and optional `id`, `kind`, `scope`, `timeout`
+/// in seconds and `system`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ToolCommand {
+    pub run: CommandLine,
+    /// Short name that makes up the action ID together with the tool name
+    pub id: Option<String>,
+    pub kind: Option<ActionKind>,
+    pub scope: Option<ActionScope>,
+    pub timeout: Option<f64>,
+    /// Variant run with `--system` that writes the tool's system-wide config,
+    /// e.g. `/etc/npmrc`. Global commands without one are skipped in that mode.
+    pub system: Option<CommandLine>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DetailedToolCommand {
+    run: CommandLine,
+    id: Option<String>,
+    kind: Option<ActionKind>,
+    scope: Option<ActionScope>,
+    timeout: Option<f64>,
+    system: Option<CommandLine>,
+}
+
+impl<'de> Deserialize<'de> for ToolCommand {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ToolCommandVisitor;
+
+        impl<'de> Visitor<'de> for ToolCommandVisitor {
+            type Value = ToolCommand;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a command string, an argv list or a map with `run`")
+            }
+
+            fn visit_str<E: de::Error>(self, run: &str) -> Result<ToolCommand, E> {
+                Ok(ToolCommand::from(run))
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> Result<ToolCommand, A::Error> {
+                let argv = Vec::<String>::deserialize(SeqAccessDeserializer::new(seq))?;
+                Ok(ToolCommand::from(CommandLine::Argv(argv)))
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<ToolCommand, A::Error> {
+                let detailed = DetailedToolCommand::deserialize(MapAccessDeserializer::new(map))?;
+                Ok(ToolCommand {
+                    run: detailed.run,
+                    id: detailed.id,
+                    kind: detailed.kind,
+                    scope: detailed.scope,
+                    timeout: detailed.timeout,
+                    system: detailed.system,
+                })
+            }
+        }
+
+        deserializer.deserialize_any(ToolCommandVisitor)
+    }
+}
+
+impl From<CommandLine> for ToolCommand {
+    fn from(run: CommandLine) -> Self {
+        ToolCommand {
+            run,
+            ..Default::default()
+        }
+    }
+}
+
+impl From<&str> for ToolCommand {
+    fn from(run: &str) -> Self {
+        ToolCommand::from(run.to_string())
+    }
+}
+
+impl From<String> for ToolCommand {
+    fn from(run: String) -> Self {
+        ToolCommand::from(CommandLine::Shell(run))
+    }
+}
+
+impl From<Vec<String>> for ToolCommand {
+    fn from(argv: Vec<String>) -> Self {
+        ToolCommand::from(CommandLine::Argv(argv))
+    }
+}
+
+impl PartialEq<&str> for ToolCommand {
+    fn eq(&self, other: &&str) -> bool {
+        self.run == *other
+    }
+}
+
+impl fmt::Display for ToolCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.run.fmt(f)
+    }
 }
@@ -0,0 +1,361 @@
+use std::sync::{Arc, Mutex};
+use stop_nagging::executor::{ExecOptions, Executor};
+use stop_nagging::export::EnvSink;
+use stop_nagging::report::{CommandOutcome, ToolStatus};
+use stop_nagging::resolved_env::ResolvedEnv;
+use stop_nagging::yaml_config::{ActionKind, CommandLine};
+use stop_nagging::{ConfigSource, Error, StopNagging};
+
+const YAML: &str = r#"
+profiles:
+  quiet:
+    kinds: [telemetry]
+ecosystems:
+  test:
+    tools:
+      - name: "first"
+        executable: "sh"
+        kind: telemetry
+        env:
+          BUILDER_TEST_FIRST: "1"
+        commands:
+          - "first command"
+      - name: "second"
+        executable: "sh"
+        kind: update_nag
+        commands:
+          - "second command"
+"#;
+
+/// Records commands instead of running them.
+#[derive(Clone, Default)]
+struct Recorder(Arc<Mutex<Vec<String>>>);
+
+impl Executor for Recorder {
+    fn run(&self, cmd: &CommandLine, _env: &ResolvedEnv, _options: &ExecOptions) -> CommandOutcome {
+        self.0.lock().unwrap().push(cmd.to_string());
+        CommandOutcome::Success
+    }
+}
+
+/// Keeps the env vars it is given.
+#[derive(Clone, Default)]
+struct Collector(Arc<Mutex<Vec<(String, String)>>>);
+
+impl EnvSink for Collector {
+    fn apply(&mut self, env: &ResolvedEnv) -> stop_nagging::Result<()> {
+        let mut vars = self.0.lock().unwrap();
+        vars.extend(
+            env.vars_to_set()
+                .map(|(k, v)| (k.to_string(), v.to_string())),
+        );
+        Ok(())
+    }
+}
+
+#[test]
+fn test_plan_runs_nothing_until_applied() {
+    let recorder = Recorder::default();
+    let collector = Collector::default();
+    let plan = StopNagging::builder()
+        .config_source(ConfigSource::Yaml(YAML.to_string()))
+        .executor(recorder.clone())
+        .env_sink(collector.clone())
+        .jobs(1)
+        .build()
+        .unwrap();
+
+    assert_eq!(plan.report().tools.len(), 2);
+    assert!(plan
+        .report()
+        .tools
+        .iter()
+        .flat_map(|tool| &tool.commands)
+        .all(|command| command.outcome == CommandOutcome::NotRun));
+    assert!(plan.render().contains("first.command-1"));
+    assert!(recorder.0.lock().unwrap().is_empty());
+    assert!(collector.0.lock().unwrap().is_empty());
+
+    let report = plan.apply().unwrap();
+    assert_eq!(
+        *recorder.0.lock().unwrap(),
+        vec!["first command", "second command"]
+    );
+    assert_eq!(report.tool("first").unwrap().status, ToolStatus::Applied);
+    assert_eq!(
+        report.tool("first").unwrap().commands[0].outcome,
+        CommandOutcome::Success
+    );
+    assert!(collector
+        .0
+        .lock()
+        .unwrap()
+        .contains(&("BUILDER_TEST_FIRST".to_string(), "1".to_string())));
+}
+
+/// Applying runs what was planned, even if the tools changed in between.
+#[cfg(unix)]
+#[test]
+fn test_apply_uses_the_planned_tools() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let executable = temp_dir.path().join("planned-tool");
+    std::fs::write(&executable, "#!/bin/sh\n").unwrap();
+    std::fs::set_permissions(&executable, std::fs::Permissions::from_mode(0o755)).unwrap();
+    let yaml = format!(
+        r#"
+ecosystems:
+  test:
+    tools:
+      - name: "planned"
+        executable: "{}"
+        kind: telemetry
+        commands:
+          - "planned command"
+"#,
+        executable.display()
+    );
+    let recorder = Recorder::default();
+    let plan = StopNagging::builder()
+        .config_source(ConfigSource::Yaml(yaml))
+        .executor(recorder.clone())
+        .build()
+        .unwrap();
+    assert_eq!(plan.report().tools[0].status, ToolStatus::Applied);
+
+    std::fs::remove_file(&executable).unwrap();
+    let report = plan.apply().unwrap();
+    assert_eq!(report.tool("planned").unwrap().status, ToolStatus::Applied);
+    assert_eq!(*recorder.0.lock().unwrap(), vec!["planned command"]);
+}
+
+#[test]
+fn test_filters_and_profile() {
+    let build = || {
+        StopNagging::builder()
+            .config_source(ConfigSource::Yaml(YAML.to_string()))
+            .executor(Recorder::default())
+    };
+    let tools = |plan: &stop_nagging::Plan| -> Vec<String> {
+        plan.report().tools.iter().map(|t| t.tool.clone()).collect()
+    };
+
+    let plan = build().ignore_tools(["second"]).build().unwrap();
+    assert_eq!(tools(&plan), vec!["first"]);
+
+    let plan = build().profile("quiet").build().unwrap();
+    assert_eq!(plan.profile().unwrap().name, "quiet");
+    assert_eq!(tools(&plan), vec!["first"]);
+
+    let plan = build()
+        .profile("quiet")
+        .kinds([ActionKind::UpdateNag])
+        .build()
+        .unwrap();
+    assert_eq!(tools(&plan), vec!["second"]);
+
+    let plan = build().skip_actions(["second.command-1"]).build().unwrap();
+    assert_eq!(tools(&plan), vec!["first"]);
+}
+
+#[test]
+fn test_later_sources_replace_tools() {
+    let overlay = r#"
+ecosystems:
+  test:
+    tools:
+      - name: "second"
+        executable: "sh"
+        kind: update_nag
+        commands:
+          - "replaced command"
+      - name: "third"
+        executable: "sh"
+        kind: update_nag
+        commands:
+          - "third command"
+"#;
+    let recorder = Recorder::default();
+    StopNagging::builder()
+        .config_source(ConfigSource::Yaml(YAML.to_string()))
+        .config_source(ConfigSource::Yaml(overlay.to_string()))
+        .executor(recorder.clone())
+        .jobs(1)
+        .build()
+        .unwrap()
+        .apply()
+        .unwrap();
+    assert_eq!(
+        *recorder.0.lock().unwrap(),
+        vec!["first command", "replaced command", "third command"]
+    );
+}
+
+#[test]
+fn test_build_errors_are_typed() {
+    let err = StopNagging::builder()
+        .config_source(ConfigSource::Yaml(YAML.to_string()))
+        .profile("missing")
+        .build()
+        .err()
+        .unwrap();
+    assert!(matches!(err, Error::UnknownProfile(name) if name == "missing"));
+
+    let err = StopNagging::builder()
+        .config_source(ConfigSource::Yaml("ecosystems: [".to_string()))
+        .build()
+        .err()
+        .unwrap();
+    assert!(matches!(err, Error::Parse { path: None, .. }));
+
+    let err = StopNagging::builder()
+        .config_file("does/not/exist.yaml")
+        .build()
+        .err()
+        .unwrap();
+    assert!(matches!(err, Error::Io { .. }));
+
+    let cyclic = r#"
+ecosystems:
+  test:
+    tools:
+      - name: a
+        executable: sh
+        after: [b]
+      - name: b
+        executable: sh
+        after: [a]
+"#;
+    let err = StopNagging::builder()
+        .config_source(ConfigSource::Yaml(cyclic.to_string()))
+        .build()
+        .err()
+        .unwrap();
+    assert!(matches!(err, Error::Invalid { .. }));
+}
+
+#[test]
+fn test_default_config_builds() {
+    let plan = StopNagging::builder()
+        .executor(Recorder::default())
+        .build()
+        .unwrap();
+    assert!(plan.warnings().is_empty());
+}
+
+#[test]
+fn test_dry_run_applies_nothing() {
+    let recorder = Recorder::default();
+    let collector = Collector::default();
+    let report = StopNagging::builder()
+        .config_source(ConfigSource::Yaml(YAML.to_string()))
+        .executor(recorder.clone())
+        .env_sink(collector.clone())
+        .dry_run(true)
+        .build()
+        .unwrap()
+        .apply()
+        .unwrap();
+
+    assert_eq!(report.tools.len(), 2);
+    assert!(report.render_plan().contains("first.command-1"));
+    assert!(recorder.0.lock().unwrap().is_empty());
+    assert!(collector.0.lock().unwrap().is_empty());
+}
+
+const PROJECT_YAML: &str = r#"
+ecosystems:
+  test:
+    tools:
+      - name: "tool"
+        executable: "sh"
+        kind: telemetry
+        commands:
+          - "global command"
+          - run: "project command"
+            scope: project
+      - name: "other"
+        executable: "sh"
+        kind: telemetry
+        commands:
+          - "other command"
+"#;
+
+#[test]
+fn test_project_is_found_from_current_dir() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    std::fs::write(temp_dir.path().join("package.json"), "{}").unwrap();
+    let nested = temp_dir.path().join("src/nested");
+    std::fs::create_dir_all(&nested).unwrap();
+
+    let run = |builder: stop_nagging::Builder| {
+        let recorder = Recorder::default();
+        builder
+            .config_source(ConfigSource::Yaml(PROJECT_YAML.to_string()))
+            .executor(recorder.clone())
+            .ignore_tools(["other"])
+            .build()
+            .unwrap()
+            .apply()
+            .unwrap();
+        let commands = recorder.0.lock().unwrap().clone();
+        commands
+    };
+
+    assert_eq!(
+        run(StopNagging::builder().current_dir(&nested)),
+        vec!["global command", "project command"]
+    );
+    // Without a project only global actions apply
+    assert_eq!(run(StopNagging::builder()), vec!["global command"]);
+}
+
+#[test]
+fn test_project_config() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    std::fs::write(temp_dir.path().join("package.json"), "{}").unwrap();
+    let nested = temp_dir.path().join("src");
+    std::fs::create_dir_all(&nested).unwrap();
+
+    let project = StopNagging::builder()
+        .config_source(ConfigSource::Yaml(PROJECT_YAML.to_string()))
+        .ignore_tools(["other"])
+        .current_dir(&nested)
+        .project()
+        .unwrap();
+
+    assert_eq!(project.root.as_deref(), Some(temp_dir.path()));
+    assert_eq!(project.dirs, vec![nested, temp_dir.path().to_path_buf()]);
+    let tools: Vec<&str> = project.config.ecosystems["test"]
+        .tools
+        .iter()
+        .map(|tool| tool.name.as_str())
+        .collect();
+    assert_eq!(tools, vec!["tool"]);
+}
+
+#[test]
+fn test_lenient_build_reports_errors_as_warnings() {
+    let yaml = r#"
+ecosystems:
+  test:
+    tools:
+      - name: "kindless"
+        executable: "sh"
+        commands:
+          - "kindless command"
+"#;
+    let build = || {
+        StopNagging::builder()
+            .config_source(ConfigSource::Yaml(yaml.to_string()))
+            .executor(Recorder::default())
+    };
+
+    assert!(matches!(build().build(), Err(Error::Invalid { .. })));
+    let plan = build().strict(false).build().unwrap();
+    assert!(plan
+        .warnings()
+        .iter()
+        .any(|issue| issue.message.contains("has no kind")));
+}
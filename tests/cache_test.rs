@@ -0,0 +1,51 @@
+use std::fs;
+use stop_nagging::cache::{self, Cache};
+use tempfile::TempDir;
+
+#[test]
+fn test_cache_round_trip() {
+    let temp_dir = TempDir::new().unwrap();
+    let path = cache::file_in(temp_dir.path());
+
+    let mut cache = Cache::load(&path);
+    assert!(!cache.is_fresh("npm", "abc"));
+    cache.record("npm", "abc");
+    cache.save().unwrap();
+
+    let cache = Cache::load(&path);
+    assert!(cache.is_fresh("npm", "abc"));
+    assert!(!cache.is_fresh("npm", "def"));
+}
+
+#[test]
+fn test_corrupt_cache_is_empty() {
+    let temp_dir = TempDir::new().unwrap();
+    let path = temp_dir.path().join("fingerprints.json");
+    fs::write(&path, "not json").unwrap();
+
+    assert!(!Cache::load(&path).is_fresh("npm", "abc"));
+}
+
+#[test]
+fn test_unchanged_cache_is_not_written() {
+    let temp_dir = TempDir::new().unwrap();
+    let path = cache::file_in(temp_dir.path());
+
+    Cache::load(&path).save().unwrap();
+    assert!(!path.exists());
+}
+
+#[test]
+fn test_fingerprint_changes_with_executable() {
+    let temp_dir = TempDir::new().unwrap();
+    let executable = temp_dir.path().join("tool");
+    fs::write(&executable, "v1").unwrap();
+
+    let before = cache::fingerprint("run tool", Some(&executable));
+    assert_eq!(before, cache::fingerprint("run tool", Some(&executable)));
+    assert_ne!(before, cache::fingerprint("run other", Some(&executable)));
+
+    // An upgrade replaces the file, changing its size
+    fs::write(&executable, "v2.0").unwrap();
+    assert_ne!(before, cache::fingerprint("run tool", Some(&executable)));
+}
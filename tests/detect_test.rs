@@ -0,0 +1,98 @@
+use indexmap::IndexMap;
+use std::fs;
+use stop_nagging::detect::{find_executable, has_json_path, matches};
+use stop_nagging::yaml_config::Detect;
+use tempfile::TempDir;
+
+fn project(package_json: &str) -> TempDir {
+    let temp_dir = TempDir::new().unwrap();
+    fs::write(temp_dir.path().join("package.json"), package_json).unwrap();
+    temp_dir
+}
+
+fn json_path(file: &str, paths: &[&str]) -> Detect {
+    let mut json_path = IndexMap::new();
+    json_path.insert(
+        file.to_string(),
+        paths.iter().map(|p| p.to_string()).collect(),
+    );
+    Detect {
+        json_path,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_files() {
+    let temp_dir = project("{}");
+    let detect = Detect {
+        files: vec!["poetry.lock".to_string(), "package.json".to_string()],
+        ..Default::default()
+    };
+    assert!(matches(&detect, Some(temp_dir.path())));
+    assert!(!matches(&detect, None));
+
+    let detect = Detect {
+        files: vec!["Gemfile".to_string()],
+        ..Default::default()
+    };
+    assert!(!matches(&detect, Some(temp_dir.path())));
+}
+
+#[test]
+fn test_json_path() {
+    let temp_dir = project(r#"{"devDependencies": {"next": "14.0.0"}}"#);
+    let root = Some(temp_dir.path());
+    assert!(matches(
+        &json_path(
+            "package.json",
+            &["dependencies.next", "devDependencies.next"]
+        ),
+        root
+    ));
+    assert!(!matches(
+        &json_path("package.json", &["dependencies.gatsby"]),
+        root
+    ));
+    assert!(!matches(
+        &json_path("missing.json", &["dependencies.next"]),
+        root
+    ));
+}
+
+#[test]
+fn test_has_json_path() {
+    let json = serde_json::json!({"workspaces": ["packages/*"], "name": "app"});
+    assert!(has_json_path(&json, "workspaces.0"));
+    assert!(!has_json_path(&json, "workspaces.1"));
+    assert!(!has_json_path(&json, "name.first"));
+}
+
+#[cfg(target_family = "unix")]
+#[test]
+fn test_relative_executable_is_resolved_against_project_root() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let temp_dir = project("{}");
+    let bin = temp_dir.path().join("node_modules").join(".bin");
+    fs::create_dir_all(&bin).unwrap();
+    fs::write(bin.join("next"), "#!/bin/sh\n").unwrap();
+    fs::set_permissions(bin.join("next"), fs::Permissions::from_mode(0o755)).unwrap();
+
+    assert_eq!(
+        find_executable("node_modules/.bin/next", Some(temp_dir.path())),
+        Some(bin.join("next"))
+    );
+    let detect = Detect {
+        executables: vec!["node_modules/.bin/next".to_string()],
+        ..Default::default()
+    };
+    assert!(matches(&detect, Some(temp_dir.path())));
+}
+
+#[cfg(target_family = "unix")]
+#[test]
+fn test_executable_on_path() {
+    assert!(find_executable("sh", None).is_some());
+    assert!(find_executable("stop-nagging-no-such-program", None).is_none());
+}
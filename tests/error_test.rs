@@ -0,0 +1,100 @@
+use std::process::Command;
+use std::time::Duration;
+use stop_nagging::executor;
+use stop_nagging::validate;
+use stop_nagging::yaml_config::YamlConfig;
+use stop_nagging::Error;
+
+#[test]
+fn test_parse_error_has_path_and_span() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let path = temp_dir.path().join("tools.yaml");
+    std::fs::write(&path, "ecosystems:\n  test:\n    tools: 5\n").unwrap();
+
+    let Err(Error::Parse {
+        path: Some(error_path),
+        span: Some(span),
+        ..
+    }) = YamlConfig::from_file(&path)
+    else {
+        panic!("expected a parse error");
+    };
+    assert_eq!(error_path, path);
+    assert_eq!(span.line, 3);
+}
+
+#[test]
+fn test_parse_error_shows_the_location_once() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let path = temp_dir.path().join("tools.yaml");
+    std::fs::write(&path, "ecosystems:\n  test:\n    tools: 5\n").unwrap();
+
+    let message = YamlConfig::from_file(&path).unwrap_err().to_string();
+    assert!(
+        message.starts_with(&format!("{}:3:", path.display())),
+        "{}",
+        message
+    );
+    assert!(!message.contains(" at line "), "{}", message);
+}
+
+#[test]
+fn test_parse_error_without_path_keeps_the_location() {
+    let err = YamlConfig::from_yaml("ecosystems: [1, 2\n  foo").unwrap_err();
+    let Error::Parse {
+        path: None,
+        span: Some(span),
+        ..
+    } = &err
+    else {
+        panic!("expected a parse error without a path");
+    };
+    let prefix = format!("{}:{}: ", span.line, span.column);
+    assert!(err.to_string().starts_with(&prefix), "{}", err);
+}
+
+#[test]
+fn test_missing_file_is_io_error() {
+    let err = YamlConfig::from_file("does/not/exist.yaml").unwrap_err();
+    assert!(matches!(err, Error::Io { path: Some(_), .. }));
+    assert!(err.to_string().starts_with("does/not/exist.yaml: "));
+}
+
+#[test]
+fn test_spawn_error() {
+    let command = Command::new("stop-nagging-no-such-program");
+    let err = executor::output(command, Duration::from_secs(5)).unwrap_err();
+    let Error::Spawn { command, .. } = err else {
+        panic!("expected a spawn error");
+    };
+    assert_eq!(command, "stop-nagging-no-such-program");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_timeout_error() {
+    let mut command = Command::new("sleep");
+    command.arg("5");
+    let err = executor::output(command, Duration::from_millis(100)).unwrap_err();
+    assert!(matches!(err, Error::Timeout { ref command, .. } if command == "sleep 5"));
+}
+
+#[test]
+fn test_check_fails_with_invalid_config() {
+    let yaml = r#"
+ecosystems:
+  test:
+    tools:
+      - name: a
+        executable: a
+        timeout: -1
+"#;
+    let config: YamlConfig = serde_yaml::from_str(yaml).unwrap();
+    let Err(Error::Invalid { issues }) = validate::check(&config) else {
+        panic!("expected the config to be invalid");
+    };
+    assert_eq!(issues.len(), 1);
+
+    let config = YamlConfig::from_default().unwrap();
+    assert!(validate::check(&config).unwrap().is_empty());
+}
@@ -0,0 +1,251 @@
+use std::fs;
+use std::time::{Duration, Instant};
+use stop_nagging::executor::{
+    check_command, detect_prompt, run_command, run_command_with, ExecOptions,
+};
+use stop_nagging::report::CommandOutcome;
+use stop_nagging::resolved_env::ResolvedEnv;
+use stop_nagging::Error;
+use tempfile::TempDir;
+
+#[test]
+fn test_run_command_success() {
+    let outcome = run_command("echo ok", &ResolvedEnv::default(), Duration::from_secs(5));
+    assert_eq!(outcome, CommandOutcome::Success);
+}
+
+#[test]
+fn test_run_command_failure() {
+    let outcome = run_command(
+        "echo broken >&2; exit 3",
+        &ResolvedEnv::default(),
+        Duration::from_secs(5),
+    );
+    assert_eq!(
+        outcome,
+        CommandOutcome::Failed {
+            status: Some(3),
+            stderr: "broken\n".to_string()
+        }
+    );
+}
+
+#[test]
+fn test_check_command() {
+    assert!(check_command("true", Duration::from_secs(5)));
+    assert!(!check_command("false", Duration::from_secs(5)));
+}
+
+#[cfg(target_family = "unix")]
+#[test]
+fn test_run_command_times_out() {
+    let start = Instant::now();
+    let outcome = run_command(
+        "sleep 10",
+        &ResolvedEnv::default(),
+        Duration::from_millis(200),
+    );
+    assert_eq!(
+        outcome,
+        CommandOutcome::TimedOut(Duration::from_millis(200))
+    );
+    assert!(start.elapsed() < Duration::from_secs(5));
+}
+
+#[cfg(target_family = "unix")]
+#[test]
+fn test_timeout_kills_whole_process_group() {
+    let temp_dir = TempDir::new().unwrap();
+    let pid_file = temp_dir.path().join("pid");
+
+    // The grandchild keeps the output pipes open and outlives `sh` unless the group is killed
+    let cmd = format!("sleep 30 & echo $! > {}; wait", pid_file.display());
+    let start = Instant::now();
+    let outcome = run_command(&cmd, &ResolvedEnv::default(), Duration::from_millis(300));
+    assert!(matches!(outcome, CommandOutcome::TimedOut(_)));
+    assert!(start.elapsed() < Duration::from_secs(5));
+
+    let pid = fs::read_to_string(&pid_file).unwrap();
+    // A killed process may linger as a zombie until it's reaped, which still counts as dead
+    let alive = format!("ps -o stat= -p {} | grep -qv Z", pid.trim());
+    let start = Instant::now();
+    while check_command(&alive, Duration::from_secs(1)) {
+        assert!(
+            start.elapsed() < Duration::from_secs(2),
+            "grandchild survived the timeout"
+        );
+        std::thread::sleep(Duration::from_millis(20));
+    }
+}
+
+#[cfg(target_family = "unix")]
+#[test]
+fn test_background_process_holding_the_output_counts_towards_timeout() {
+    // `sh` exits at once, but `sleep` keeps stdout and stderr open
+    let start = Instant::now();
+    let outcome = run_command(
+        "echo hi; sleep 10 &",
+        &ResolvedEnv::default(),
+        Duration::from_millis(300),
+    );
+    assert_eq!(
+        outcome,
+        CommandOutcome::TimedOut(Duration::from_millis(300))
+    );
+    assert!(start.elapsed() < Duration::from_secs(5));
+}
+
+#[cfg(target_family = "unix")]
+#[test]
+fn test_stdin_is_empty() {
+    // Would block until the timeout if stdin were inherited from a terminal
+    let outcome = run_command(
+        "test -z \"$(cat)\"",
+        &ResolvedEnv::default(),
+        Duration::from_secs(5),
+    );
+    assert_eq!(outcome, CommandOutcome::Success);
+}
+
+#[test]
+fn test_non_interactive_env_is_injected() {
+    let outcome = run_command(
+        "test \"$CI\" = 1 && test \"$DEBIAN_FRONTEND\" = noninteractive && test \"$NO_COLOR\" = 1",
+        &ResolvedEnv::default(),
+        Duration::from_secs(5),
+    );
+    assert_eq!(outcome, CommandOutcome::Success);
+}
+
+#[test]
+fn test_interactive_skips_injected_env() {
+    let options = ExecOptions {
+        timeout: Duration::from_secs(5),
+        interactive: true,
+        non_interactive_env: vec![("STOP_NAGGING_INJECTED".to_string(), "1".to_string())],
+        current_dir: None,
+        path_prefix: None,
+        extra_env: vec![],
+    };
+    let outcome = run_command_with(
+        &"test -z \"$STOP_NAGGING_INJECTED\"".into(),
+        &ResolvedEnv::default(),
+        &options,
+    );
+    assert_eq!(outcome, CommandOutcome::Success);
+
+    let options = ExecOptions {
+        interactive: false,
+        ..options
+    };
+    let outcome = run_command_with(
+        &"test \"$STOP_NAGGING_INJECTED\" = 1".into(),
+        &ResolvedEnv::default(),
+        &options,
+    );
+    assert_eq!(outcome, CommandOutcome::Success);
+}
+
+#[cfg(target_family = "unix")]
+#[test]
+fn test_argv_command_bypasses_the_shell() {
+    let temp_dir = TempDir::new().unwrap();
+    let file = temp_dir.path().join("it's $HOME; here");
+    let argv = vec!["touch".to_string(), file.to_string_lossy().to_string()];
+    let outcome = run_command_with(
+        &argv.into(),
+        &ResolvedEnv::default(),
+        &ExecOptions::new(Duration::from_secs(5)),
+    );
+    assert_eq!(outcome, CommandOutcome::Success);
+    assert!(file.exists());
+}
+
+#[test]
+fn test_argv_command_not_found() {
+    let outcome = run_command_with(
+        &vec!["stop-nagging-no-such-program".to_string()].into(),
+        &ResolvedEnv::default(),
+        &ExecOptions::new(Duration::from_secs(5)),
+    );
+    // Not installed is a spawn failure, not a command that exited non-zero
+    assert!(matches!(
+        outcome,
+        CommandOutcome::SpawnFailed(ref error) if matches!(**error, Error::Spawn { .. })
+    ));
+}
+
+#[cfg(target_family = "unix")]
+#[test]
+fn test_prompt_is_reported() {
+    let outcome = run_command(
+        "printf 'Continue? [y/N] '; read answer",
+        &ResolvedEnv::default(),
+        Duration::from_secs(5),
+    );
+    assert_eq!(
+        outcome,
+        CommandOutcome::NeedsInteraction("Continue? [y/N]".to_string())
+    );
+}
+
+#[cfg(target_family = "unix")]
+#[test]
+fn test_prompt_before_timeout_is_reported() {
+    let outcome = run_command(
+        "printf 'Enter a value: '; sleep 10",
+        &ResolvedEnv::default(),
+        Duration::from_millis(300),
+    );
+    assert_eq!(
+        outcome,
+        CommandOutcome::NeedsInteraction("Enter a value:".to_string())
+    );
+}
+
+#[cfg(target_family = "unix")]
+#[test]
+fn test_success_mentioning_a_password_is_a_success() {
+    let outcome = run_command(
+        "echo 'Updated password: stored in keychain'",
+        &ResolvedEnv::default(),
+        Duration::from_secs(5),
+    );
+    assert_eq!(outcome, CommandOutcome::Success);
+}
+
+#[cfg(target_family = "unix")]
+#[test]
+fn test_failure_ending_in_colon_is_a_failure() {
+    let outcome = run_command(
+        "echo 'error: could not read config:' >&2; exit 2",
+        &ResolvedEnv::default(),
+        Duration::from_secs(5),
+    );
+    assert_eq!(
+        outcome,
+        CommandOutcome::Failed {
+            status: Some(2),
+            stderr: "error: could not read config:\n".to_string()
+        }
+    );
+}
+
+#[test]
+fn test_detect_prompt() {
+    assert_eq!(
+        detect_prompt(b"Overwrite settings? (y/n)", b"", false),
+        Some("Overwrite settings? (y/n)".to_string())
+    );
+    assert_eq!(
+        detect_prompt(b"", b"Which project?\n\n", true),
+        Some("Which project?".to_string())
+    );
+    // A question or colon in the output of a command that exited is just output
+    assert_eq!(detect_prompt(b"Did you know?\n", b"", false), None);
+    assert_eq!(
+        detect_prompt(b"", b"error: could not read config:\n", false),
+        None
+    );
+    assert_eq!(detect_prompt(b"done\n", b"error: bad flag\n", true), None);
+}
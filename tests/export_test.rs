@@ -0,0 +1,97 @@
+use std::collections::HashMap;
+use stop_nagging::export::{self, EnvFile};
+use stop_nagging::resolved_env::ResolvedEnv;
+use stop_nagging::yaml_config::{EnvMode, EnvSeparator, EnvSetting, Tool};
+
+fn env(vars: &[(&str, &str)]) -> ResolvedEnv {
+    let tool = Tool {
+        name: "test-tool".to_string(),
+        executable: "echo".to_string(),
+        env: vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), (*v).into()))
+            .collect(),
+        ..Default::default()
+    };
+    ResolvedEnv::resolve([&tool], &HashMap::new(), false)
+}
+
+#[test]
+fn test_section_is_appended_to_existing_file() {
+    let merged = export::merge_env_file(
+        "use nix\n",
+        &env(&[("A_VAR", "1"), ("B_VAR", "two words")]),
+        EnvFile::Envrc,
+    );
+    assert_eq!(
+        merged,
+        "use nix\n\n\
+         # >>> stop-nagging >>>\n\
+         export A_VAR='1'\n\
+         export B_VAR='two words'\n\
+         # <<< stop-nagging <<<\n"
+    );
+}
+
+#[test]
+fn test_section_is_replaced_in_place() {
+    let existing = "FIRST=1\n\
+                    # >>> stop-nagging >>>\n\
+                    OLD_VAR=1\n\
+                    # <<< stop-nagging <<<\n\
+                    LAST=1\n";
+    let merged = export::merge_env_file(existing, &env(&[("NEW_VAR", "1")]), EnvFile::Dotenv);
+    assert_eq!(
+        merged,
+        "FIRST=1\n\
+         # >>> stop-nagging >>>\n\
+         NEW_VAR=1\n\
+         # <<< stop-nagging <<<\n\
+         LAST=1\n"
+    );
+    // Running again changes nothing
+    assert_eq!(
+        export::merge_env_file(&merged, &env(&[("NEW_VAR", "1")]), EnvFile::Dotenv),
+        merged
+    );
+}
+
+#[test]
+fn test_keys_set_by_hand_are_not_duplicated() {
+    let existing = "export A_VAR=0\nB_VAR=0\n";
+    let merged = export::merge_env_file(
+        existing,
+        &env(&[("A_VAR", "1"), ("B_VAR", "1"), ("C_VAR", "1")]),
+        EnvFile::Envrc,
+    );
+    assert!(merged.starts_with(existing));
+    assert!(merged.contains("export C_VAR='1'\n"));
+    assert_eq!(merged.matches("A_VAR").count(), 1);
+    assert_eq!(merged.matches("B_VAR").count(), 1);
+}
+
+#[test]
+fn test_dotenv_skips_appended_tokens() {
+    let tool = Tool {
+        name: "gradle".to_string(),
+        executable: "echo".to_string(),
+        env: [(
+            "GRADLE_OPTS".to_string(),
+            EnvSetting {
+                value: "-Dquiet=true".to_string(),
+                mode: EnvMode::AppendToken,
+                separator: EnvSeparator::Space,
+                ..Default::default()
+            },
+        )]
+        .into_iter()
+        .collect(),
+        ..Default::default()
+    };
+    let env = ResolvedEnv::resolve([&tool], &HashMap::new(), false);
+
+    let dotenv = export::merge_env_file("", &env, EnvFile::Dotenv);
+    assert!(!dotenv.contains("GRADLE_OPTS"));
+    let envrc = export::merge_env_file("", &env, EnvFile::Envrc);
+    assert!(envrc.contains("export GRADLE_OPTS="));
+}
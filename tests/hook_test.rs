@@ -0,0 +1,138 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use stop_nagging::hook::{self, Shell};
+use stop_nagging::yaml_config::YamlConfig;
+use tempfile::TempDir;
+
+fn next_project(root: &Path) {
+    fs::write(
+        root.join("package.json"),
+        r#"{"devDependencies": {"next": "14.0.0"}}"#,
+    )
+    .unwrap();
+    install_next(root);
+}
+
+/// Puts `next` into `node_modules/.bin`, like `npm install` does.
+fn install_next(root: &Path) {
+    let bin = root.join("node_modules").join(".bin");
+    fs::create_dir_all(&bin).unwrap();
+    fs::write(bin.join("next"), "#!/bin/sh\n").unwrap();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(bin.join("next"), fs::Permissions::from_mode(0o755)).unwrap();
+    }
+}
+
+#[test]
+fn test_project_env_in_next_project() {
+    let temp_dir = TempDir::new().unwrap();
+    next_project(temp_dir.path());
+    let config = YamlConfig::from_default().unwrap();
+
+    let env = hook::project_env(&config, &[temp_dir.path().to_path_buf()], &HashMap::new());
+    assert_eq!(
+        env,
+        vec![("NEXT_TELEMETRY_DISABLED".to_string(), "1".to_string())]
+    );
+}
+
+#[test]
+fn test_no_project_env_outside_projects() {
+    let temp_dir = TempDir::new().unwrap();
+    let config = YamlConfig::from_default().unwrap();
+
+    assert!(
+        hook::project_env(&config, &[temp_dir.path().to_path_buf()], &HashMap::new()).is_empty()
+    );
+    assert!(hook::project_env(&config, &[], &HashMap::new()).is_empty());
+}
+
+#[test]
+fn test_stamp_changes_with_project_files() {
+    let temp_dir = TempDir::new().unwrap();
+    let config = YamlConfig::from_default().unwrap();
+
+    let before = hook::stamp(&config, &[temp_dir.path().to_path_buf()]);
+    assert_eq!(
+        before,
+        hook::stamp(&config, &[temp_dir.path().to_path_buf()])
+    );
+    next_project(temp_dir.path());
+    assert_ne!(
+        before,
+        hook::stamp(&config, &[temp_dir.path().to_path_buf()])
+    );
+}
+
+#[test]
+fn test_stamp_changes_when_the_executable_is_installed() {
+    let temp_dir = TempDir::new().unwrap();
+    let config = YamlConfig::from_default().unwrap();
+    next_project(temp_dir.path());
+    let bin = temp_dir.path().join("node_modules").join(".bin");
+    fs::remove_dir_all(&bin).unwrap();
+
+    let before = hook::stamp(&config, &[temp_dir.path().to_path_buf()]);
+    install_next(temp_dir.path());
+    assert_ne!(
+        before,
+        hook::stamp(&config, &[temp_dir.path().to_path_buf()])
+    );
+}
+
+#[test]
+fn test_render_update_restores_vars_no_longer_wanted() {
+    let previous = hook::previous_vars(Some(
+        r#"{"NEXT_TELEMETRY_DISABLED": null, "GATSBY_TELEMETRY_DISABLED": "0"}"#,
+    ));
+    let wanted = vec![("NEXT_TELEMETRY_DISABLED".to_string(), "1".to_string())];
+    let base = hook::user_env(
+        [("GATSBY_TELEMETRY_DISABLED".to_string(), "1".to_string())],
+        &previous,
+    );
+
+    assert_eq!(
+        hook::render_update(Shell::Bash, &previous, &wanted, &base, "abc"),
+        "export GATSBY_TELEMETRY_DISABLED='0'\n\
+         export NEXT_TELEMETRY_DISABLED='1'\n\
+         export STOP_NAGGING_PROJECT_VARS='{\"NEXT_TELEMETRY_DISABLED\":null}'\n\
+         export STOP_NAGGING_PROJECT_STAMP='abc'\n"
+    );
+    assert_eq!(
+        hook::render_update(Shell::Fish, &previous, &[], &base, "abc"),
+        "set -gx GATSBY_TELEMETRY_DISABLED '0'\n\
+         set -e NEXT_TELEMETRY_DISABLED\n\
+         set -e STOP_NAGGING_PROJECT_VARS\n\
+         set -gx STOP_NAGGING_PROJECT_STAMP 'abc'\n"
+    );
+}
+
+#[test]
+fn test_render_update_saves_the_value_it_overrides() {
+    let previous = hook::previous_vars(None);
+    let base = hook::user_env(
+        [("NEXT_TELEMETRY_DISABLED".to_string(), "0".to_string())],
+        &previous,
+    );
+    let wanted = vec![("NEXT_TELEMETRY_DISABLED".to_string(), "1".to_string())];
+
+    let update = hook::render_update(Shell::Bash, &previous, &wanted, &base, "abc");
+    assert!(
+        update.contains(r#"export STOP_NAGGING_PROJECT_VARS='{"NEXT_TELEMETRY_DISABLED":"0"}'"#)
+    );
+}
+
+#[test]
+fn test_render_hook_calls_hook_env() {
+    for (shell, name) in [
+        (Shell::Bash, "bash"),
+        (Shell::Zsh, "zsh"),
+        (Shell::Fish, "fish"),
+    ] {
+        let script = hook::render_hook(shell, "/usr/bin/stop-nagging");
+        assert!(script.contains(&format!("'/usr/bin/stop-nagging' hook-env {}", name)));
+    }
+}
@@ -1,9 +1,17 @@
 use assert_cmd::Command;
 use predicates::prelude::*;
 
+/// The CLI without the fingerprint cache, so test runs neither skip tools
+/// nor leave entries in the real `~/.cache`.
+fn stop_nagging() -> Command {
+    let mut cmd = Command::cargo_bin("stop-nagging").unwrap();
+    cmd.arg("--no-cache");
+    cmd
+}
+
 #[test]
 fn test_stop_nagging_cli_help() {
-    let mut cmd = Command::cargo_bin("stop-nagging").unwrap();
+    let mut cmd = stop_nagging();
     cmd.arg("--help");
     cmd.assert().success().stdout(predicate::str::contains(
         "A CLI tool to silence or disable upgrade/advertising nags",
@@ -12,14 +20,14 @@ fn test_stop_nagging_cli_help() {
 
 #[test]
 fn test_stop_nagging_cli_with_sample_yaml() {
-    let mut cmd = Command::cargo_bin("stop-nagging").unwrap();
+    let mut cmd = stop_nagging();
     cmd.arg("--yaml").arg("tests/test_files/sample_tools.yaml");
     cmd.assert().success();
 }
 
 #[test]
 fn test_stop_nagging_cli_with_ignore_tools() {
-    let mut cmd = Command::cargo_bin("stop-nagging").unwrap();
+    let mut cmd = stop_nagging();
     cmd.arg("--yaml")
         .arg("tests/test_files/sample_tools.yaml")
         .arg("--ignore-tools")
@@ -29,10 +37,639 @@ fn test_stop_nagging_cli_with_ignore_tools() {
 
 #[test]
 fn test_stop_nagging_cli_with_ecosystems() {
-    let mut cmd = Command::cargo_bin("stop-nagging").unwrap();
+    let mut cmd = stop_nagging();
     cmd.arg("--yaml")
         .arg("tests/test_files/sample_tools.yaml")
         .arg("--ecosystems")
         .arg("test");
     cmd.assert().success();
 }
+
+#[test]
+fn test_stop_nagging_cli_print_env() {
+    let mut cmd = stop_nagging();
+    cmd.arg("--yaml")
+        .arg("tests/test_files/sample_tools.yaml")
+        .arg("--print-env");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("export SAMPLE_ENV='1'"));
+}
+
+#[test]
+fn test_stop_nagging_cli_validate() {
+    let mut cmd = stop_nagging();
+    cmd.arg("validate");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Configuration is valid"));
+}
+
+#[test]
+fn test_stop_nagging_cli_validate_reports_unparsable_yaml() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let yaml = temp_dir.path().join("bad.yaml");
+    std::fs::write(&yaml, "ecosystems:\n  test:\n    tools: 5\n").unwrap();
+
+    let mut cmd = stop_nagging();
+    cmd.arg("--yaml").arg(&yaml).arg("validate");
+    cmd.assert()
+        .code(1)
+        .stdout(predicate::str::contains("Configuration is valid").not())
+        .stderr(predicate::str::contains("bad.yaml"));
+}
+
+#[test]
+fn test_stop_nagging_cli_print_env_skips_non_interactive_env() {
+    let mut cmd = stop_nagging();
+    cmd.arg("--yaml")
+        .arg("tests/test_files/sample_tools.yaml")
+        .arg("--print-env");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("DEBIAN_FRONTEND").not());
+}
+
+#[test]
+fn test_stop_nagging_cli_dry_run_shows_action_ids() {
+    let mut cmd = stop_nagging();
+    cmd.arg("--yaml")
+        .arg("tests/test_files/sample_tools.yaml")
+        .arg("--dry-run");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("echo_test.SAMPLE_ENV"))
+        .stdout(predicate::str::contains("echo_test.command-1"));
+}
+
+#[test]
+fn test_stop_nagging_cli_skip_actions() {
+    let mut cmd = stop_nagging();
+    cmd.arg("--yaml")
+        .arg("tests/test_files/sample_tools.yaml")
+        .arg("--skip-actions")
+        .arg("echo_test.SAMPLE_ENV")
+        .arg("--print-env");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("SAMPLE_ENV").not());
+}
+
+#[test]
+fn test_stop_nagging_cli_reports_profile() {
+    let mut cmd = stop_nagging();
+    cmd.arg("--profile")
+        .arg("dev")
+        .arg("--dry-run")
+        .arg("--verbose");
+    cmd.assert().success().stderr(predicate::str::contains(
+        "Using profile 'dev' (selected with --profile)",
+    ));
+}
+
+#[test]
+fn test_stop_nagging_cli_unknown_profile() {
+    let mut cmd = stop_nagging();
+    cmd.arg("--profile").arg("nope").arg("--dry-run");
+    cmd.assert()
+        .success()
+        .stderr(predicate::str::contains("Unknown profile 'nope'"));
+}
+
+#[test]
+fn test_stop_nagging_cli_home_redirects_tool_writes() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let home = temp_dir.path().join("home");
+    let yaml = temp_dir.path().join("tools.yaml");
+    std::fs::write(
+        &yaml,
+        r#"
+ecosystems:
+  test:
+    tools:
+      - name: "writer"
+        executable: "sh"
+        kind: telemetry
+        commands:
+          - 'touch "$HOME/.writerrc" "$XDG_CONFIG_HOME/writer.conf"'
+"#,
+    )
+    .unwrap();
+    std::fs::create_dir_all(home.join(".config")).unwrap();
+
+    let mut cmd = stop_nagging();
+    cmd.arg("--yaml").arg(&yaml).arg("--home").arg(&home);
+    cmd.assert().success();
+    assert!(home.join(".writerrc").exists());
+    assert!(home.join(".config").join("writer.conf").exists());
+    // `--no-cache` leaves no fingerprints behind
+    assert!(!home.join(".cache").exists());
+}
+
+/// Runs the built-in npm action against a temporary home. Without npm, a
+/// stand-in that writes `.npmrc` like `npm config set` takes its place.
+#[cfg(unix)]
+#[test]
+fn test_stop_nagging_cli_builtin_config_in_temp_home() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let home = temp_dir.path().join("home");
+    let bin = temp_dir.path().join("bin");
+    std::fs::create_dir(&bin).unwrap();
+    let has_npm = std::process::Command::new("npm")
+        .arg("--version")
+        .output()
+        .is_ok_and(|output| output.status.success());
+    if !has_npm {
+        let stand_ins = [
+            ("node", "#!/bin/sh\n"),
+            (
+                "npm",
+                "#!/bin/sh\n\
+                 [ \"$1 $2\" = 'config set' ] && echo \"$3=$4\" >> \"$HOME/.npmrc\"\n",
+            ),
+        ];
+        for (name, script) in stand_ins {
+            let path = bin.join(name);
+            std::fs::write(&path, script).unwrap();
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        }
+    }
+    let path = std::env::join_paths(
+        std::iter::once(bin).chain(std::env::split_paths(&std::env::var_os("PATH").unwrap())),
+    )
+    .unwrap();
+
+    let mut cmd = stop_nagging();
+    cmd.env("PATH", path)
+        .arg("--home")
+        .arg(&home)
+        .arg("--no-toolchains")
+        .arg("--only-actions")
+        .arg("npm.update-notifier");
+    cmd.assert().success();
+
+    let npmrc = std::fs::read_to_string(home.join(".npmrc")).unwrap();
+    assert!(npmrc.contains("update-notifier=false"));
+}
+
+#[test]
+fn test_stop_nagging_cli_root_moves_home() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let root = temp_dir.path();
+    let yaml = root.join("tools.yaml");
+    std::fs::write(
+        &yaml,
+        r#"
+ecosystems:
+  test:
+    tools:
+      - name: "writer"
+        executable: "sh"
+        kind: telemetry
+        commands:
+          - 'touch "$HOME/.writerrc"'
+"#,
+    )
+    .unwrap();
+
+    let mut cmd = stop_nagging();
+    cmd.env("HOME", "/home/builder")
+        .arg("--yaml")
+        .arg(&yaml)
+        .arg("--root")
+        .arg(root);
+    cmd.assert().success();
+    assert!(root.join("home/builder/.writerrc").exists());
+}
+
+#[test]
+fn test_stop_nagging_cli_system_writes_env_files_under_root() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let root = temp_dir.path();
+
+    let mut cmd = stop_nagging();
+    cmd.env_remove("SAMPLE_ENV")
+        .arg("--yaml")
+        .arg("tests/test_files/sample_tools.yaml")
+        .arg("--system")
+        .arg("--root")
+        .arg(root);
+    cmd.assert().success();
+
+    let profile = std::fs::read_to_string(root.join("etc/profile.d/stop-nagging.sh")).unwrap();
+    assert!(profile.contains("export SAMPLE_ENV='1'"));
+    let environment =
+        std::fs::read_to_string(root.join("etc/environment.d/50-stop-nagging.conf")).unwrap();
+    assert!(environment.contains("SAMPLE_ENV=\"1\""));
+}
+
+#[test]
+fn test_stop_nagging_cli_system_dry_run_lists_files() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+
+    let mut cmd = stop_nagging();
+    cmd.arg("--yaml")
+        .arg("tests/test_files/sample_tools.yaml")
+        .arg("--system")
+        .arg("--root")
+        .arg(temp_dir.path())
+        .arg("--dry-run");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("etc/profile.d/stop-nagging.sh"));
+    assert!(!temp_dir.path().join("etc").exists());
+}
+
+#[test]
+fn test_stop_nagging_cli_hook_env_exports_project_vars() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let root = temp_dir.path();
+    std::fs::create_dir(root.join(".git")).unwrap();
+    std::fs::write(
+        root.join("package.json"),
+        r#"{"dependencies": {"next": "14"}}"#,
+    )
+    .unwrap();
+    std::fs::create_dir_all(root.join("node_modules/.bin")).unwrap();
+    let next = root.join("node_modules/.bin/next");
+    std::fs::write(&next, "#!/bin/sh\n").unwrap();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&next, std::fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    let mut cmd = stop_nagging();
+    cmd.current_dir(root)
+        .env_remove("NEXT_TELEMETRY_DISABLED")
+        .env_remove("STOP_NAGGING_PROJECT_STAMP")
+        .args(["hook-env", "bash"]);
+    cmd.assert().success().stdout(predicate::str::contains(
+        "export NEXT_TELEMETRY_DISABLED='1'",
+    ));
+}
+
+/// The bash hook picks up a project file created in the current directory
+/// at the next prompt, without changing directories.
+#[cfg(unix)]
+#[test]
+fn test_stop_nagging_cli_bash_hook_runs_on_every_prompt() {
+    use std::os::unix::fs::PermissionsExt;
+
+    if std::process::Command::new("bash")
+        .arg("--version")
+        .output()
+        .is_err()
+    {
+        return;
+    }
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let root = temp_dir.path();
+    std::fs::create_dir(root.join(".git")).unwrap();
+    std::fs::create_dir_all(root.join("node_modules/.bin")).unwrap();
+    let next = root.join("node_modules/.bin/next");
+    std::fs::write(&next, "#!/bin/sh\n").unwrap();
+    std::fs::set_permissions(&next, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+    let exe = assert_cmd::cargo::cargo_bin("stop-nagging");
+    let script = format!(
+        r#"eval "$('{exe}' hook bash)"
+_stop_nagging_hook
+echo "before=${{NEXT_TELEMETRY_DISABLED-}}"
+echo '{{"dependencies": {{"next": "14"}}}}' > package.json
+_stop_nagging_hook
+echo "after=${{NEXT_TELEMETRY_DISABLED-}}"
+"#,
+        exe = exe.display()
+    );
+    let output = std::process::Command::new("bash")
+        .args(["--norc", "--noprofile", "-c", &script])
+        .current_dir(root)
+        .env_remove("NEXT_TELEMETRY_DISABLED")
+        .env_remove("STOP_NAGGING_PROJECT_VARS")
+        .env_remove("STOP_NAGGING_PROJECT_STAMP")
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("before=\n"), "{}", stdout);
+    assert!(stdout.contains("after=1\n"), "{}", stdout);
+}
+
+/// Leaving a project puts back the value the user had before the hook
+/// overrode it.
+#[cfg(unix)]
+#[test]
+fn test_stop_nagging_cli_bash_hook_restores_vars_on_leave() {
+    use std::os::unix::fs::PermissionsExt;
+
+    if std::process::Command::new("bash")
+        .arg("--version")
+        .output()
+        .is_err()
+    {
+        return;
+    }
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let project = temp_dir.path().join("app");
+    std::fs::create_dir_all(project.join(".git")).unwrap();
+    std::fs::write(
+        project.join("package.json"),
+        r#"{"dependencies": {"next": "14"}}"#,
+    )
+    .unwrap();
+    std::fs::create_dir_all(project.join("node_modules/.bin")).unwrap();
+    let next = project.join("node_modules/.bin/next");
+    std::fs::write(&next, "#!/bin/sh\n").unwrap();
+    std::fs::set_permissions(&next, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+    let exe = assert_cmd::cargo::cargo_bin("stop-nagging");
+    let script = format!(
+        r#"eval "$('{exe}' hook bash)"
+cd app
+_stop_nagging_hook
+echo "inside=$NEXT_TELEMETRY_DISABLED"
+cd ..
+_stop_nagging_hook
+echo "outside=$NEXT_TELEMETRY_DISABLED"
+"#,
+        exe = exe.display()
+    );
+    let output = std::process::Command::new("bash")
+        .args(["--norc", "--noprofile", "-c", &script])
+        .current_dir(temp_dir.path())
+        .env("NEXT_TELEMETRY_DISABLED", "0")
+        .env_remove("STOP_NAGGING_PROJECT_VARS")
+        .env_remove("STOP_NAGGING_PROJECT_STAMP")
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("inside=1\n"), "{}", stdout);
+    assert!(stdout.contains("outside=0\n"), "{}", stdout);
+}
+
+/// Inside `apps/web` of a monorepo, detection uses the app's own
+/// `package.json` rather than the repository root.
+#[test]
+fn test_stop_nagging_cli_hook_env_in_monorepo_subdirectory() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let root = temp_dir.path();
+    std::fs::create_dir(root.join(".git")).unwrap();
+    let app = root.join("apps").join("web");
+    std::fs::create_dir_all(app.join("node_modules/.bin")).unwrap();
+    std::fs::write(
+        app.join("package.json"),
+        r#"{"dependencies": {"next": "14"}}"#,
+    )
+    .unwrap();
+    let next = app.join("node_modules/.bin/next");
+    std::fs::write(&next, "#!/bin/sh\n").unwrap();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&next, std::fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    let mut cmd = stop_nagging();
+    cmd.current_dir(&app)
+        .env_remove("NEXT_TELEMETRY_DISABLED")
+        .env_remove("STOP_NAGGING_PROJECT_VARS")
+        .env_remove("STOP_NAGGING_PROJECT_STAMP")
+        .args(["hook-env", "bash"]);
+    cmd.assert().success().stdout(predicate::str::contains(
+        "export NEXT_TELEMETRY_DISABLED='1'",
+    ));
+
+    let mut cmd = stop_nagging();
+    cmd.current_dir(&app)
+        .args(["--dry-run", "--ecosystems", "js_frameworks"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("next (js_frameworks)"))
+        .stdout(predicate::str::contains("next (js_frameworks): not available").not());
+}
+
+#[test]
+fn test_stop_nagging_cli_export_dotenv() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let root = temp_dir.path();
+    std::fs::create_dir(root.join(".git")).unwrap();
+    std::fs::write(
+        root.join("package.json"),
+        r#"{"dependencies": {"next": "14"}}"#,
+    )
+    .unwrap();
+    std::fs::create_dir_all(root.join("node_modules/.bin")).unwrap();
+    let next = root.join("node_modules/.bin/next");
+    std::fs::write(&next, "#!/bin/sh\n").unwrap();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&next, std::fs::Permissions::from_mode(0o755)).unwrap();
+    }
+    let output = root.join(".env");
+    std::fs::write(&output, "DATABASE_URL=postgres://localhost\n").unwrap();
+
+    for _ in 0..2 {
+        let mut cmd = stop_nagging();
+        // What the exporting user has set must not end up in the file
+        cmd.env("NEXT_TELEMETRY_DISABLED", "0")
+            .arg("--project-dir")
+            .arg(root)
+            .args(["export", "dotenv"]);
+        cmd.assert().success();
+    }
+
+    let contents = std::fs::read_to_string(&output).unwrap();
+    assert!(contents.starts_with("DATABASE_URL=postgres://localhost\n"));
+    assert_eq!(contents.matches("NEXT_TELEMETRY_DISABLED=1").count(), 1);
+    assert_eq!(contents.matches("# >>> stop-nagging >>>").count(), 1);
+    // Tools that aren't tied to the project belong in the shell profile
+    assert!(
+        !contents.contains("NPM_CONFIG_UPDATE_NOTIFIER"),
+        "{}",
+        contents
+    );
+    assert!(
+        !contents.contains("PIP_DISABLE_PIP_VERSION_CHECK"),
+        "{}",
+        contents
+    );
+}
+
+#[test]
+fn test_stop_nagging_cli_shims_install() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let dir = temp_dir.path().join("shims");
+
+    let mut cmd = stop_nagging();
+    cmd.arg("--yaml")
+        .arg("tests/test_files/sample_tools.yaml")
+        .args(["shims", "install"])
+        .arg(&dir);
+    cmd.assert().success();
+
+    let shim = std::fs::read_to_string(dir.join("echo")).unwrap();
+    assert!(shim.contains("export SAMPLE_ENV='1'"));
+
+    let mut cmd = stop_nagging();
+    cmd.args(["shims", "list"]).arg(&dir);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("echo"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_stop_nagging_cli_wrap_filters_nags_and_keeps_exit_code() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let yaml = temp_dir.path().join("tools.yaml");
+    std::fs::write(
+        &yaml,
+        r#"
+ecosystems:
+  test:
+    tools:
+      - name: "fake"
+        executable: "fake-npm"
+        kind: update_nag
+        nag_patterns:
+          - "^fake notice New version available"
+          - "^fake notice Run fake-npm upgrade"
+"#,
+    )
+    .unwrap();
+    let fake = temp_dir.path().join("fake-npm");
+    std::fs::write(
+        &fake,
+        "#!/bin/sh\n\
+echo 'installed 3 packages'\n\
+echo 'fake notice New version available: 2.0' >&2\n\
+printf '\\033[33mfake notice\\033[0m Run fake-npm upgrade\\n' >&2\n\
+echo 'warning: deprecated flag' >&2\n\
+echo 'done'\n\
+exit 3\n",
+    )
+    .unwrap();
+    std::fs::set_permissions(&fake, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+    let mut cmd = stop_nagging();
+    cmd.arg("--yaml")
+        .arg(&yaml)
+        .arg("wrap")
+        .arg("--")
+        .arg(&fake);
+    cmd.assert()
+        .code(3)
+        .stdout("installed 3 packages\ndone\n")
+        .stderr("warning: deprecated flag\n");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_stop_nagging_cli_selftest_with_local_installer() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let yaml = temp_dir.path().join("tools.yaml");
+    std::fs::write(
+        &yaml,
+        r#"
+ecosystems:
+  test:
+    tools:
+      - name: "quiet"
+        executable: "stand-in"
+        kind: update_nag
+        env:
+          STAND_IN_QUIET: "1"
+        nag_patterns: ["^new version available"]
+        nag_probe: "stand-in"
+      - name: "loud"
+        executable: "stand-in"
+        kind: update_nag
+        nag_patterns: ["^new version available"]
+        nag_probe: "stand-in"
+"#,
+    )
+    .unwrap();
+    let installer = r#"printf '#!/bin/sh\n[ "$STAND_IN_QUIET" = 1 ] || echo "new version available"\n' > "$STOP_NAGGING_PREFIX/bin/stand-in" && chmod +x "$STOP_NAGGING_PREFIX/bin/stand-in""#;
+
+    let mut cmd = stop_nagging();
+    cmd.arg("--yaml")
+        .arg(&yaml)
+        .args(["selftest", "--tool", "quiet", "--installer", installer]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("quiet: passed"));
+
+    let mut cmd = stop_nagging();
+    cmd.arg("--yaml")
+        .arg(&yaml)
+        .args(["selftest", "--installer", installer]);
+    cmd.assert()
+        .code(1)
+        .stdout(predicate::str::contains("quiet: passed"))
+        .stdout(predicate::str::contains("loud: FAILED"));
+}
+
+/// An interactive command reads its answer from the terminal. `script` gives
+/// stop-nagging a pseudo-terminal; the test is skipped without util-linux's.
+#[cfg(unix)]
+#[test]
+fn test_stop_nagging_cli_interactive_command_reads_the_terminal() {
+    use std::io::Write;
+    use std::process::Stdio;
+
+    let has_script = std::process::Command::new("script")
+        .arg("--version")
+        .output()
+        .is_ok_and(|output| output.status.success());
+    if !has_script {
+        return;
+    }
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let answer = temp_dir.path().join("answer");
+    let yaml = temp_dir.path().join("tools.yaml");
+    std::fs::write(
+        &yaml,
+        format!(
+            r#"
+ecosystems:
+  test:
+    tools:
+      - name: "asker"
+        executable: "sh"
+        kind: telemetry
+        interactive: true
+        timeout: 10
+        commands:
+          - 'printf "Share usage data? "; read reply; echo "$reply" > "{}"'
+"#,
+            answer.display()
+        ),
+    )
+    .unwrap();
+
+    let mut child = std::process::Command::new("script")
+        .arg("-qec")
+        .arg(format!(
+            "{} --no-cache --yaml {}",
+            env!("CARGO_BIN_EXE_stop-nagging"),
+            yaml.display()
+        ))
+        .arg("/dev/null")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(b"no\n").unwrap();
+    let output = child.wait_with_output().unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(stdout.contains("Share usage data?"), "{}", stdout);
+    assert!(!stdout.contains("timed out"), "{}", stdout);
+    assert_eq!(std::fs::read_to_string(&answer).unwrap(), "no\n");
+}
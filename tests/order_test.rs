@@ -0,0 +1,141 @@
+use stop_nagging::order::{declaration_order, tool_order};
+use stop_nagging::validate::{validate, Severity};
+use stop_nagging::yaml_config::YamlConfig;
+use stop_nagging::Error;
+
+fn names(config: &YamlConfig) -> Vec<String> {
+    tool_order(config)
+        .unwrap()
+        .iter()
+        .map(|t| t.tool.name.clone())
+        .collect()
+}
+
+#[test]
+fn test_declaration_order_is_preserved() {
+    let yaml = r#"
+ecosystems:
+  zeta:
+    tools:
+      - name: z1
+        executable: z1
+      - name: z2
+        executable: z2
+  alpha:
+    tools:
+      - name: a1
+        executable: a1
+  middle:
+    tools:
+      - name: m1
+        executable: m1
+"#;
+    let config: YamlConfig = serde_yaml::from_str(yaml).unwrap();
+    let ecosystems: Vec<&String> = config.ecosystems.keys().collect();
+    assert_eq!(ecosystems, vec!["zeta", "alpha", "middle"]);
+    assert_eq!(names(&config), vec!["z1", "z2", "a1", "m1"]);
+    assert_eq!(declaration_order(&config).len(), 4);
+}
+
+#[test]
+fn test_after_moves_tool_behind_dependency() {
+    let yaml = r#"
+ecosystems:
+  nodejs:
+    tools:
+      - name: yarn
+        executable: yarn
+        after: [corepack]
+      - name: npm
+        executable: npm
+      - name: corepack
+        executable: corepack
+      - name: pnpm
+        executable: pnpm
+        after: [corepack]
+"#;
+    let config: YamlConfig = serde_yaml::from_str(yaml).unwrap();
+    assert_eq!(names(&config), vec!["npm", "corepack", "yarn", "pnpm"]);
+}
+
+#[test]
+fn test_after_across_ecosystems() {
+    let yaml = r#"
+ecosystems:
+  first:
+    tools:
+      - name: late
+        executable: late
+        after: [early]
+  second:
+    tools:
+      - name: early
+        executable: early
+"#;
+    let config: YamlConfig = serde_yaml::from_str(yaml).unwrap();
+    let ordered = tool_order(&config).unwrap();
+    assert_eq!(ordered[0].tool.name, "early");
+    assert_eq!(ordered[0].ecosystem, "second");
+    assert_eq!(ordered[1].tool.name, "late");
+}
+
+#[test]
+fn test_cycle_is_detected() {
+    let yaml = r#"
+ecosystems:
+  test:
+    tools:
+      - name: standalone
+        executable: standalone
+      - name: a
+        executable: a
+        after: [b]
+      - name: b
+        executable: b
+        after: [a]
+"#;
+    let config: YamlConfig = serde_yaml::from_str(yaml).unwrap();
+    let Err(Error::Cycle(cycle)) = tool_order(&config) else {
+        panic!("expected a cycle");
+    };
+    assert_eq!(cycle.first(), cycle.last());
+    assert!(cycle.contains(&"a".to_string()));
+    assert!(cycle.contains(&"b".to_string()));
+
+    let issues = validate(&config);
+    assert!(issues
+        .iter()
+        .any(|issue| issue.severity == Severity::Error && issue.message.contains("cycle")));
+}
+
+#[test]
+fn test_unknown_after_is_reported() {
+    let yaml = r#"
+ecosystems:
+  test:
+    tools:
+      - name: a
+        executable: a
+        after: [missing]
+"#;
+    let config: YamlConfig = serde_yaml::from_str(yaml).unwrap();
+    assert_eq!(names(&config), vec!["a"]);
+
+    let issues = validate(&config);
+    assert_eq!(issues.len(), 1);
+    assert_eq!(issues[0].severity, Severity::Warning);
+    assert!(issues[0].message.contains("missing"));
+}
+
+#[test]
+fn test_default_config_orders_corepack_first() {
+    let config = YamlConfig::from_default().unwrap();
+    let names: Vec<String> = tool_order(&config)
+        .unwrap()
+        .iter()
+        .map(|t| t.tool.name.clone())
+        .collect();
+    let position = |name: &str| names.iter().position(|n| n == name).unwrap();
+    assert!(position("corepack") < position("yarn"));
+    assert!(position("corepack") < position("pnpm"));
+}
@@ -0,0 +1,165 @@
+use indexmap::IndexMap;
+use std::fs;
+use std::time::{Duration, Instant};
+use stop_nagging::parallel::run_ordered;
+use stop_nagging::report::{CommandOutcome, ToolStatus};
+use stop_nagging::runner::Runner;
+use stop_nagging::yaml_config::{Ecosystem, Tool, YamlConfig};
+use tempfile::TempDir;
+
+fn sleepy_config(count: usize, seconds: &str) -> YamlConfig {
+    let tools = (0..count)
+        .map(|i| Tool {
+            name: format!("sleepy-{}", i),
+            executable: "sh".to_string(),
+            commands: vec![format!("sleep {}", seconds).into()],
+            ..Default::default()
+        })
+        .collect();
+    let mut ecosystems = IndexMap::new();
+    ecosystems.insert(
+        "test-ecosystem".to_string(),
+        Ecosystem {
+            check_ecosystem: None,
+            tools,
+            ..Default::default()
+        },
+    );
+    YamlConfig {
+        ecosystems,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_run_ordered_keeps_item_order() {
+    let items: Vec<u64> = vec![30, 10, 20, 0];
+    let results = run_ordered(&items, &[], 4, |&ms| {
+        std::thread::sleep(Duration::from_millis(ms));
+        ms * 2
+    });
+    assert_eq!(results, vec![60, 20, 40, 0]);
+}
+
+#[test]
+fn test_run_ordered_waits_for_dependencies() {
+    let items = vec!["first", "second"];
+    let deps = vec![vec![], vec![0]];
+    let start = Instant::now();
+    let finished_at = run_ordered(&items, &deps, 2, |&name| {
+        if name == "first" {
+            std::thread::sleep(Duration::from_millis(200));
+        }
+        start.elapsed()
+    });
+    assert!(finished_at[1] >= finished_at[0]);
+}
+
+#[cfg(target_family = "unix")]
+#[test]
+fn test_parallel_run_is_faster() {
+    let serial_start = Instant::now();
+    Runner::new(sleepy_config(4, "0.5"), vec![], vec![], false)
+        .with_jobs(1)
+        .run();
+    let serial = serial_start.elapsed();
+
+    let parallel_start = Instant::now();
+    let report = Runner::new(sleepy_config(4, "0.5"), vec![], vec![], false)
+        .with_jobs(4)
+        .run();
+    let parallel = parallel_start.elapsed();
+
+    assert!(
+        serial >= Duration::from_millis(2000),
+        "serial took {:?}",
+        serial
+    );
+    assert!(
+        parallel < Duration::from_millis(1500),
+        "parallel took {:?}",
+        parallel
+    );
+
+    // The report stays in declaration order
+    let names: Vec<&str> = report.tools.iter().map(|t| t.tool.as_str()).collect();
+    assert_eq!(names, vec!["sleepy-0", "sleepy-1", "sleepy-2", "sleepy-3"]);
+    assert!(report.tools.iter().all(|t| t.status == ToolStatus::Applied
+        && t.commands
+            .iter()
+            .all(|c| c.outcome == CommandOutcome::Success)));
+}
+
+#[cfg(target_family = "unix")]
+#[test]
+fn test_parallel_run_respects_after() {
+    let temp_dir = TempDir::new().unwrap();
+    let log = temp_dir.path().join("order.log");
+    let log = log.to_str().unwrap();
+
+    let tools = vec![
+        Tool {
+            name: "second".to_string(),
+            executable: "sh".to_string(),
+            commands: vec![format!("echo second >> {}", log).into()],
+            after: vec!["first".to_string()],
+            ..Default::default()
+        },
+        Tool {
+            name: "first".to_string(),
+            executable: "sh".to_string(),
+            commands: vec![format!("sleep 0.3 && echo first >> {}", log).into()],
+            ..Default::default()
+        },
+    ];
+    let mut ecosystems = IndexMap::new();
+    ecosystems.insert(
+        "test-ecosystem".to_string(),
+        Ecosystem {
+            check_ecosystem: None,
+            tools,
+            ..Default::default()
+        },
+    );
+    let config = YamlConfig {
+        ecosystems,
+        ..Default::default()
+    };
+
+    Runner::new(config, vec![], vec![], false)
+        .with_jobs(4)
+        .run();
+    assert_eq!(fs::read_to_string(log).unwrap(), "first\nsecond\n");
+}
+
+#[test]
+fn test_unavailable_tool_is_reported() {
+    let tools = vec![Tool {
+        name: "missing-tool".to_string(),
+        executable: "non-existent-tool-12345".to_string(),
+        commands: vec!["non-existent-command".into()],
+        ..Default::default()
+    }];
+    let mut ecosystems = IndexMap::new();
+    ecosystems.insert(
+        "test-ecosystem".to_string(),
+        Ecosystem {
+            check_ecosystem: None,
+            tools,
+            ..Default::default()
+        },
+    );
+    let report = Runner::new(
+        YamlConfig {
+            ecosystems,
+            ..Default::default()
+        },
+        vec![],
+        vec![],
+        false,
+    )
+    .run();
+    let tool = report.tool("missing-tool").unwrap();
+    assert_eq!(tool.status, ToolStatus::NotAvailable);
+    assert!(tool.commands.is_empty());
+}
@@ -0,0 +1,65 @@
+use std::path::{Path, PathBuf};
+use stop_nagging::paths::Paths;
+
+#[test]
+fn test_system_paths_under_root() {
+    let paths = Paths {
+        home: None,
+        root: Some(PathBuf::from("/mnt/image")),
+    };
+    assert_eq!(
+        paths.system("/etc/npmrc"),
+        PathBuf::from("/mnt/image/etc/npmrc")
+    );
+    assert_eq!(
+        Paths::default().system("/etc/npmrc"),
+        PathBuf::from("/etc/npmrc")
+    );
+}
+
+#[test]
+fn test_home_moves_under_root() {
+    let root = Path::new("/mnt/image");
+    let real_home = Path::new("/home/builder");
+    assert_eq!(
+        Paths::resolve_home(None, Some(root), Some(real_home)),
+        Some(PathBuf::from("/mnt/image/home/builder"))
+    );
+    assert_eq!(
+        Paths::resolve_home(Some(PathBuf::from("/tmp/h")), Some(root), Some(real_home)),
+        Some(PathBuf::from("/tmp/h"))
+    );
+    assert_eq!(Paths::resolve_home(None, None, Some(real_home)), None);
+}
+
+#[test]
+fn test_child_env() {
+    assert!(Paths::default().child_env().is_empty());
+
+    let paths = Paths {
+        home: Some(PathBuf::from("/tmp/h")),
+        root: Some(PathBuf::from("/mnt/image")),
+    };
+    let env = paths.child_env();
+    let get = |key: &str| env.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str());
+    assert_eq!(get("HOME"), Some("/tmp/h"));
+    assert_eq!(get("XDG_CONFIG_HOME"), Some("/tmp/h/.config"));
+    assert_eq!(get("GIT_CONFIG_SYSTEM"), Some("/mnt/image/etc/gitconfig"));
+}
+
+#[test]
+fn test_only_root_aware_system_commands_are_redirected() {
+    use stop_nagging::yaml_config::CommandLine;
+
+    let argv = |args: &[&str]| CommandLine::Argv(args.iter().map(|a| a.to_string()).collect());
+    let paths = Paths {
+        home: None,
+        root: Some(PathBuf::from("/mnt/image")),
+    };
+    assert!(paths.redirects_system_config(&argv(&["npm", "config", "set", "x", "y"])));
+    assert!(paths.redirects_system_config(&"git config --system x y".into()));
+    assert!(!paths.redirects_system_config(&argv(&["conda", "config", "--system"])));
+    assert!(!paths.redirects_system_config(&"pip config --global set x y".into()));
+    // Without --root every system command writes to the real locations anyway
+    assert!(Paths::default().redirects_system_config(&argv(&["conda", "config", "--system"])));
+}
@@ -0,0 +1,95 @@
+use indexmap::IndexMap;
+use std::collections::HashMap;
+use stop_nagging::profile::{choose, detect_ci, Detected};
+use stop_nagging::yaml_config::{ActionKind, Profile, YamlConfig};
+use stop_nagging::Error;
+
+fn config() -> YamlConfig {
+    let mut profiles = IndexMap::new();
+    for (name, kinds) in [
+        ("dev", vec![ActionKind::UpdateNag]),
+        ("ci", vec![ActionKind::UpdateNag, ActionKind::Prompt]),
+    ] {
+        profiles.insert(
+            name.to_string(),
+            Profile {
+                kinds,
+                ..Default::default()
+            },
+        );
+    }
+    YamlConfig {
+        profiles,
+        ..Default::default()
+    }
+}
+
+fn vars(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+    pairs
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect()
+}
+
+#[test]
+fn test_detect_ci() {
+    assert_eq!(detect_ci(&vars(&[])), None);
+    assert_eq!(detect_ci(&vars(&[("CI", "false")])), None);
+    assert_eq!(detect_ci(&vars(&[("CI", "true")])), Some("CI".to_string()));
+    assert_eq!(
+        detect_ci(&vars(&[("GITHUB_ACTIONS", "true")])),
+        Some("GITHUB_ACTIONS".to_string())
+    );
+}
+
+#[test]
+fn test_requested_profile_wins() {
+    let detected = Detected {
+        ci_var: Some("CI".to_string()),
+        ..Default::default()
+    };
+    let active = choose(&config(), Some("dev"), &detected).unwrap().unwrap();
+    assert_eq!(active.name, "dev");
+    assert_eq!(active.reason, "selected with --profile");
+}
+
+#[test]
+fn test_unknown_requested_profile() {
+    assert!(matches!(
+        choose(&config(), Some("nope"), &Detected::default()),
+        Err(Error::UnknownProfile(name)) if name == "nope"
+    ));
+}
+
+#[test]
+fn test_ci_is_detected() {
+    let detected = Detected {
+        ci_var: Some("GITHUB_ACTIONS".to_string()),
+        container_marker: Some("/.dockerenv".to_string()),
+    };
+    let active = choose(&config(), None, &detected).unwrap().unwrap();
+    assert_eq!(active.name, "ci");
+    assert_eq!(active.reason, "GITHUB_ACTIONS is set");
+}
+
+#[test]
+fn test_missing_container_profile_falls_back_to_dev() {
+    let detected = Detected {
+        container_marker: Some("/.dockerenv".to_string()),
+        ..Default::default()
+    };
+    let active = choose(&config(), None, &detected).unwrap().unwrap();
+    assert_eq!(active.name, "dev");
+    assert_eq!(active.reason, "default");
+}
+
+#[test]
+fn test_no_profiles() {
+    let detected = Detected {
+        ci_var: Some("CI".to_string()),
+        ..Default::default()
+    };
+    assert!(choose(&YamlConfig::default(), None, &detected)
+        .unwrap()
+        .is_none());
+}
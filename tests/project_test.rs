@@ -0,0 +1,50 @@
+use std::fs;
+use std::path::Path;
+use stop_nagging::project::{find_root, search_dirs};
+use tempfile::TempDir;
+
+#[test]
+fn test_git_root_wins_over_nearer_marker() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path();
+    fs::create_dir(root.join(".git")).unwrap();
+    let package = root.join("packages").join("app");
+    fs::create_dir_all(package.join("src")).unwrap();
+    fs::write(package.join("package.json"), "{}").unwrap();
+
+    assert_eq!(find_root(&package.join("src")), Some(root.to_path_buf()));
+}
+
+#[test]
+fn test_marker_file_without_git() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path().join("project");
+    fs::create_dir_all(root.join("src")).unwrap();
+    fs::write(root.join("Cargo.toml"), "").unwrap();
+
+    assert_eq!(find_root(&root.join("src")), Some(root));
+}
+
+#[test]
+fn test_no_project_root() {
+    let temp_dir = TempDir::new().unwrap();
+    assert_eq!(find_root(temp_dir.path()), None);
+}
+
+#[test]
+fn test_search_dirs_walk_up_to_the_root() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path();
+    let app = root.join("apps").join("web");
+
+    assert_eq!(
+        search_dirs(&app, root),
+        vec![app.clone(), root.join("apps"), root.to_path_buf()]
+    );
+    assert_eq!(search_dirs(root, root), vec![root.to_path_buf()]);
+    // A start outside the project only searches the root
+    assert_eq!(
+        search_dirs(Path::new("/elsewhere"), root),
+        vec![root.to_path_buf()]
+    );
+}
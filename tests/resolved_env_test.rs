@@ -0,0 +1,266 @@
+use std::collections::HashMap;
+use stop_nagging::export;
+use stop_nagging::resolved_env::{EnvStatus, ResolvedEnv};
+use stop_nagging::yaml_config::{EnvMode, EnvPolicy, EnvSeparator, EnvSetting, Tool};
+
+fn tool_with_env(name: &str, env: &[(&str, &str)]) -> Tool {
+    Tool {
+        name: name.to_string(),
+        executable: "echo".to_string(),
+        env: env
+            .iter()
+            .map(|(k, v)| (k.to_string(), (*v).into()))
+            .collect(),
+        commands: vec![],
+        skip: false,
+        install_for_testing: None,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_resolve_single_tool() {
+    let tool = tool_with_env("test-tool", &[("TEST_VAR", "1")]);
+    let env = ResolvedEnv::resolve([&tool], &HashMap::new(), false);
+
+    let entry = env.get("TEST_VAR").unwrap();
+    assert_eq!(entry.value, "1");
+    assert_eq!(entry.tool, "test-tool");
+    assert!(!entry.pre_existing);
+    assert!(env.conflicts().is_empty());
+}
+
+#[test]
+fn test_two_tools_same_var_is_conflict() {
+    let first = tool_with_env("first", &[("SHARED_VAR", "1")]);
+    let second = tool_with_env("second", &[("SHARED_VAR", "0")]);
+    let env = ResolvedEnv::resolve([&first, &second], &HashMap::new(), false);
+
+    assert_eq!(env.conflicts().len(), 1);
+    let conflict = &env.conflicts()[0];
+    assert_eq!(conflict.key, "SHARED_VAR");
+    assert_eq!(conflict.kept_tool, "first");
+    assert_eq!(conflict.dropped_tool, "second");
+    assert_eq!(conflict.dropped_value, "0");
+
+    // The first tool keeps the variable
+    assert_eq!(env.get("SHARED_VAR").unwrap().value, "1");
+}
+
+#[test]
+fn test_earlier_tool_does_not_count_as_pre_existing() {
+    let first = tool_with_env("first", &[("SHARED_VAR", "1")]);
+    let second = tool_with_env("second", &[("SHARED_VAR", "1")]);
+    let env = ResolvedEnv::resolve([&first, &second], &HashMap::new(), false);
+
+    assert!(!env.get("SHARED_VAR").unwrap().pre_existing);
+    assert_eq!(env.conflicts().len(), 1);
+}
+
+#[test]
+fn test_pre_existing_var_is_not_set() {
+    let tool = tool_with_env("test-tool", &[("ALREADY_SET", "1"), ("NEW_VAR", "1")]);
+    let mut base = HashMap::new();
+    base.insert("ALREADY_SET".to_string(), "0".to_string());
+    let env = ResolvedEnv::resolve([&tool], &base, false);
+
+    assert!(env.get("ALREADY_SET").unwrap().pre_existing);
+    let to_set: Vec<_> = env.vars_to_set().collect();
+    assert_eq!(to_set, vec![("NEW_VAR", "1")]);
+}
+
+#[test]
+fn test_render_posix() {
+    let tool = tool_with_env("test-tool", &[("B_VAR", "it's"), ("A_VAR", "1")]);
+    let env = ResolvedEnv::resolve([&tool], &HashMap::new(), false);
+
+    assert_eq!(
+        export::render_posix(&env),
+        "export A_VAR='1'\nexport B_VAR='it'\\''s'\n"
+    );
+}
+
+fn base_env(vars: &[(&str, &str)]) -> HashMap<String, String> {
+    vars.iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect()
+}
+
+fn tool_with_policy(value: &str, policy: EnvPolicy) -> Tool {
+    let mut tool = tool_with_env("test-tool", &[]);
+    tool.env.insert(
+        "TELEMETRY_DISABLED".to_string(),
+        EnvSetting {
+            value: value.to_string(),
+            policy,
+            ..Default::default()
+        },
+    );
+    tool
+}
+
+#[test]
+fn test_existing_desired_value_is_ok() {
+    let tool = tool_with_env("test-tool", &[("TELEMETRY_DISABLED", "1")]);
+    let base = base_env(&[("TELEMETRY_DISABLED", "1")]);
+    let env = ResolvedEnv::resolve([&tool], &base, false);
+
+    assert_eq!(
+        env.get("TELEMETRY_DISABLED").unwrap().status,
+        EnvStatus::AlreadySet
+    );
+    assert_eq!(env.vars_to_set().count(), 0);
+}
+
+#[test]
+fn test_policy_keep() {
+    let tool = tool_with_policy("1", EnvPolicy::Keep);
+    let base = base_env(&[("TELEMETRY_DISABLED", "0")]);
+    let env = ResolvedEnv::resolve([&tool], &base, false);
+
+    assert_eq!(
+        env.get("TELEMETRY_DISABLED").unwrap().status,
+        EnvStatus::Kept
+    );
+    assert_eq!(env.vars_to_set().count(), 0);
+}
+
+#[test]
+fn test_policy_override() {
+    let tool = tool_with_policy("1", EnvPolicy::Override);
+    let base = base_env(&[("TELEMETRY_DISABLED", "custom")]);
+    let env = ResolvedEnv::resolve([&tool], &base, false);
+
+    let entry = env.get("TELEMETRY_DISABLED").unwrap();
+    assert_eq!(entry.status, EnvStatus::Overridden);
+    assert_eq!(entry.existing.as_deref(), Some("custom"));
+    let to_set: Vec<_> = env.vars_to_set().collect();
+    assert_eq!(to_set, vec![("TELEMETRY_DISABLED", "1")]);
+}
+
+#[test]
+fn test_policy_override_if_enabled() {
+    let tool = tool_with_policy("1", EnvPolicy::OverrideIfEnabled);
+
+    let enabled = base_env(&[("TELEMETRY_DISABLED", "0")]);
+    let env = ResolvedEnv::resolve([&tool], &enabled, false);
+    assert_eq!(
+        env.get("TELEMETRY_DISABLED").unwrap().status,
+        EnvStatus::Overridden
+    );
+
+    let unrecognized = base_env(&[("TELEMETRY_DISABLED", "custom")]);
+    let env = ResolvedEnv::resolve([&tool], &unrecognized, false);
+    assert_eq!(
+        env.get("TELEMETRY_DISABLED").unwrap().status,
+        EnvStatus::Kept
+    );
+}
+
+#[test]
+fn test_force_env_overrides_keep() {
+    let tool = tool_with_policy("1", EnvPolicy::Keep);
+    let base = base_env(&[("TELEMETRY_DISABLED", "0")]);
+    let env = ResolvedEnv::resolve([&tool], &base, true);
+
+    assert_eq!(
+        env.get("TELEMETRY_DISABLED").unwrap().status,
+        EnvStatus::Overridden
+    );
+}
+
+fn tool_with_token(key: &str, token: &str, separator: EnvSeparator) -> Tool {
+    let mut tool = tool_with_env("test-tool", &[]);
+    tool.env.insert(
+        key.to_string(),
+        EnvSetting {
+            value: token.to_string(),
+            mode: EnvMode::AppendToken,
+            separator,
+            ..Default::default()
+        },
+    );
+    tool
+}
+
+#[test]
+fn test_append_token_to_existing_value() {
+    let tool = tool_with_token("GRADLE_OPTS", "-Dquiet=true", EnvSeparator::Space);
+    let base = base_env(&[("GRADLE_OPTS", "-Xmx2g")]);
+    let env = ResolvedEnv::resolve([&tool], &base, false);
+
+    let entry = env.get("GRADLE_OPTS").unwrap();
+    assert_eq!(entry.status, EnvStatus::Appended);
+    let to_set: Vec<_> = env.vars_to_set().collect();
+    assert_eq!(to_set, vec![("GRADLE_OPTS", "-Xmx2g -Dquiet=true")]);
+}
+
+#[test]
+fn test_append_token_is_not_duplicated() {
+    let tool = tool_with_token("GRADLE_OPTS", "-Dquiet=true", EnvSeparator::Space);
+    let base = base_env(&[("GRADLE_OPTS", "-Xmx2g  -Dquiet=true")]);
+    let env = ResolvedEnv::resolve([&tool], &base, false);
+
+    assert_eq!(
+        env.get("GRADLE_OPTS").unwrap().status,
+        EnvStatus::AlreadySet
+    );
+    assert_eq!(env.vars_to_set().count(), 0);
+}
+
+#[test]
+fn test_append_token_path_separator() {
+    let tool = tool_with_token("TOOL_PATH", "/opt/quiet", EnvSeparator::Path);
+    let sep = EnvSeparator::Path.as_str();
+    let base = base_env(&[("TOOL_PATH", &format!("/usr/lib{}/opt/quiet", sep))]);
+    let env = ResolvedEnv::resolve([&tool], &base, false);
+    assert_eq!(env.get("TOOL_PATH").unwrap().status, EnvStatus::AlreadySet);
+
+    let base = base_env(&[("TOOL_PATH", "/usr/lib")]);
+    let env = ResolvedEnv::resolve([&tool], &base, false);
+    assert_eq!(
+        env.get("TOOL_PATH").unwrap().effective,
+        format!("/usr/lib{}/opt/quiet", sep)
+    );
+}
+
+#[test]
+fn test_render_posix_append_token_keeps_existing_value() {
+    let tool = tool_with_token("GRADLE_OPTS", "-Dquiet=true", EnvSeparator::Space);
+    let env = ResolvedEnv::resolve([&tool], &HashMap::new(), false);
+
+    let rendered = export::render_posix(&env);
+    assert_eq!(
+        rendered,
+        "export GRADLE_OPTS=\"${GRADLE_OPTS:+${GRADLE_OPTS} }\"'-Dquiet=true'\n"
+    );
+}
+
+#[test]
+fn test_system_files_include_values_already_set_here() {
+    let tool = tool_with_env(
+        "test-tool",
+        &[("ALREADY_SET", "1"), ("QUOTED", "a \"b\" $c")],
+    );
+    let env = ResolvedEnv::resolve([&tool], &base_env(&[("ALREADY_SET", "1")]), false);
+
+    assert_eq!(
+        export::render_profile_script(&env),
+        "# Written by stop-nagging --system\nexport ALREADY_SET='1'\nexport QUOTED='a \"b\" $c'\n"
+    );
+    assert_eq!(
+        export::render_environment_d(&env),
+        "# Written by stop-nagging --system\nALREADY_SET=\"1\"\nQUOTED=\"a \\\"b\\\" \\$c\"\n"
+    );
+}
+
+#[test]
+fn test_environment_d_append_token() {
+    let tool = tool_with_token("GRADLE_OPTS", "-Dquiet=true", EnvSeparator::Space);
+    let env = ResolvedEnv::resolve([&tool], &HashMap::new(), false);
+
+    assert_eq!(
+        export::render_environment_d(&env),
+        "# Written by stop-nagging --system\nGRADLE_OPTS=\"${GRADLE_OPTS:+${GRADLE_OPTS} }-Dquiet=true\"\n"
+    );
+}
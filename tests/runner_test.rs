@@ -1,24 +1,29 @@
-use std::collections::HashMap;
+use indexmap::IndexMap;
 use stop_nagging::runner::Runner;
 use stop_nagging::yaml_config::{Ecosystem, Tool, YamlConfig};
 
 #[test]
 fn test_basic_tool() {
-    let mut ecosystems = HashMap::new();
+    let mut ecosystems = IndexMap::new();
     let tool = Tool {
         name: "test-tool".to_string(),
         executable: "echo".to_string(),
-        env: HashMap::new(),
-        commands: vec!["echo test".to_string()],
+        env: IndexMap::new(),
+        commands: vec!["echo test".into()],
         skip: false,
         install_for_testing: None,
+        ..Default::default()
     };
     let ecosystem = Ecosystem {
         check_ecosystem: None,
         tools: vec![tool],
+        ..Default::default()
     };
     ecosystems.insert("test-ecosystem".to_string(), ecosystem);
-    let config = YamlConfig { ecosystems };
+    let config = YamlConfig {
+        ecosystems,
+        ..Default::default()
+    };
 
     let runner = Runner::new(config, vec![], vec![], false);
     runner.run();
@@ -26,9 +31,9 @@ fn test_basic_tool() {
 
 #[test]
 fn test_env_vars() {
-    let mut ecosystems = HashMap::new();
-    let mut env = HashMap::new();
-    env.insert("TEST_VAR".to_string(), "test_value".to_string());
+    let mut ecosystems = IndexMap::new();
+    let mut env = IndexMap::new();
+    env.insert("TEST_VAR".to_string(), "test_value".into());
     let tool = Tool {
         name: "test-tool".to_string(),
         executable: "echo".to_string(),
@@ -36,13 +41,18 @@ fn test_env_vars() {
         commands: vec![],
         skip: false,
         install_for_testing: None,
+        ..Default::default()
     };
     let ecosystem = Ecosystem {
         check_ecosystem: None,
         tools: vec![tool],
+        ..Default::default()
     };
     ecosystems.insert("test-ecosystem".to_string(), ecosystem);
-    let config = YamlConfig { ecosystems };
+    let config = YamlConfig {
+        ecosystems,
+        ..Default::default()
+    };
 
     let runner = Runner::new(config, vec![], vec![], false);
     runner.run();
@@ -50,21 +60,26 @@ fn test_env_vars() {
 
 #[test]
 fn test_ignore_tool() {
-    let mut ecosystems = HashMap::new();
+    let mut ecosystems = IndexMap::new();
     let tool = Tool {
         name: "test-tool".to_string(),
         executable: "non-existent-tool-12345".to_string(),
-        env: HashMap::new(),
-        commands: vec!["non-existent-command".to_string()],
+        env: IndexMap::new(),
+        commands: vec!["non-existent-command".into()],
         skip: false,
         install_for_testing: None,
+        ..Default::default()
     };
     let ecosystem = Ecosystem {
         check_ecosystem: None,
         tools: vec![tool],
+        ..Default::default()
     };
     ecosystems.insert("test-ecosystem".to_string(), ecosystem);
-    let config = YamlConfig { ecosystems };
+    let config = YamlConfig {
+        ecosystems,
+        ..Default::default()
+    };
 
     let runner = Runner::new(config, vec!["test-tool".to_string()], vec![], false);
     runner.run();
@@ -72,21 +87,26 @@ fn test_ignore_tool() {
 
 #[test]
 fn test_ecosystem_selection() {
-    let mut ecosystems = HashMap::new();
+    let mut ecosystems = IndexMap::new();
     let tool = Tool {
         name: "test-tool".to_string(),
         executable: "non-existent-tool-12345".to_string(),
-        env: HashMap::new(),
-        commands: vec!["non-existent-command".to_string()],
+        env: IndexMap::new(),
+        commands: vec!["non-existent-command".into()],
         skip: false,
         install_for_testing: None,
+        ..Default::default()
     };
     let ecosystem = Ecosystem {
         check_ecosystem: None,
         tools: vec![tool],
+        ..Default::default()
     };
     ecosystems.insert("test-ecosystem".to_string(), ecosystem);
-    let config = YamlConfig { ecosystems };
+    let config = YamlConfig {
+        ecosystems,
+        ..Default::default()
+    };
 
     let runner = Runner::new(config, vec![], vec!["other-ecosystem".to_string()], false);
     runner.run();
@@ -94,21 +114,26 @@ fn test_ecosystem_selection() {
 
 #[test]
 fn test_ecosystem_check() {
-    let mut ecosystems = HashMap::new();
+    let mut ecosystems = IndexMap::new();
     let tool = Tool {
         name: "test-tool".to_string(),
         executable: "echo".to_string(),
-        env: HashMap::new(),
-        commands: vec!["echo test".to_string()],
+        env: IndexMap::new(),
+        commands: vec!["echo test".into()],
         skip: false,
         install_for_testing: None,
+        ..Default::default()
     };
     let ecosystem = Ecosystem {
         check_ecosystem: Some("false".to_string()),
         tools: vec![tool],
+        ..Default::default()
     };
     ecosystems.insert("test-ecosystem".to_string(), ecosystem);
-    let config = YamlConfig { ecosystems };
+    let config = YamlConfig {
+        ecosystems,
+        ..Default::default()
+    };
 
     let runner = Runner::new(config, vec![], vec![], false);
     runner.run();
@@ -116,22 +141,636 @@ fn test_ecosystem_check() {
 
 #[test]
 fn test_tool_installation() {
-    let mut ecosystems = HashMap::new();
+    let mut ecosystems = IndexMap::new();
     let tool = Tool {
         name: "test-tool".to_string(),
         executable: "echo".to_string(),
-        env: HashMap::new(),
-        commands: vec!["echo test".to_string()],
+        env: IndexMap::new(),
+        commands: vec!["echo test".into()],
         skip: false,
         install_for_testing: Some("echo 'Installing test tool'".to_string()),
+        ..Default::default()
     };
     let ecosystem = Ecosystem {
         check_ecosystem: None,
         tools: vec![tool],
+        ..Default::default()
     };
     ecosystems.insert("test-ecosystem".to_string(), ecosystem);
-    let config = YamlConfig { ecosystems };
+    let config = YamlConfig {
+        ecosystems,
+        ..Default::default()
+    };
 
     let runner = Runner::new(config, vec![], vec![], false);
     runner.run();
 }
+
+#[test]
+fn test_env_conflict_between_tools() {
+    let mut ecosystems = IndexMap::new();
+    let mut tools = Vec::new();
+    for (name, value) in [("first-tool", "1"), ("second-tool", "0")] {
+        let mut env = IndexMap::new();
+        env.insert("STOP_NAGGING_CONFLICT_VAR".to_string(), value.into());
+        tools.push(Tool {
+            name: name.to_string(),
+            executable: "echo".to_string(),
+            env,
+            commands: vec![],
+            skip: false,
+            install_for_testing: None,
+            ..Default::default()
+        });
+    }
+    let ecosystem = Ecosystem {
+        check_ecosystem: None,
+        tools,
+        ..Default::default()
+    };
+    ecosystems.insert("test-ecosystem".to_string(), ecosystem);
+    let config = YamlConfig {
+        ecosystems,
+        ..Default::default()
+    };
+
+    let runner = Runner::new(config, vec![], vec![], false);
+    let env = runner.run().env;
+    assert_eq!(env.conflicts().len(), 1);
+    assert_eq!(env.conflicts()[0].dropped_tool, "second-tool");
+    // Nothing leaks into the test process itself
+    assert!(std::env::var("STOP_NAGGING_CONFLICT_VAR").is_err());
+}
+
+#[test]
+fn test_after_decides_which_tool_claims_env() {
+    let mut ecosystems = IndexMap::new();
+    let mut tools = Vec::new();
+    for (name, after) in [
+        ("declared-first", vec!["declared-second"]),
+        ("declared-second", vec![]),
+    ] {
+        let mut env = IndexMap::new();
+        env.insert("STOP_NAGGING_ORDER_VAR".to_string(), name.into());
+        tools.push(Tool {
+            name: name.to_string(),
+            executable: "echo".to_string(),
+            env,
+            after: after.into_iter().map(String::from).collect(),
+            ..Default::default()
+        });
+    }
+    let ecosystem = Ecosystem {
+        check_ecosystem: None,
+        tools,
+        ..Default::default()
+    };
+    ecosystems.insert("test-ecosystem".to_string(), ecosystem);
+    let config = YamlConfig {
+        ecosystems,
+        ..Default::default()
+    };
+
+    let runner = Runner::new(config, vec![], vec![], false);
+    let env = runner.run().env;
+    assert_eq!(
+        env.get("STOP_NAGGING_ORDER_VAR").unwrap().tool,
+        "declared-second"
+    );
+}
+
+#[cfg(target_family = "unix")]
+#[test]
+fn test_command_timeout_is_reported() {
+    use std::time::Duration;
+    use stop_nagging::report::CommandOutcome;
+    use stop_nagging::yaml_config::ToolCommand;
+
+    let mut ecosystems = IndexMap::new();
+    let tool = Tool {
+        name: "slow-tool".to_string(),
+        executable: "sh".to_string(),
+        commands: vec![
+            "sleep 10".into(),
+            ToolCommand {
+                run: "sleep 10".into(),
+                timeout: Some(0.1),
+                ..Default::default()
+            },
+        ],
+        timeout: Some(0.2),
+        ..Default::default()
+    };
+    ecosystems.insert(
+        "test-ecosystem".to_string(),
+        Ecosystem {
+            check_ecosystem: None,
+            tools: vec![tool],
+            ..Default::default()
+        },
+    );
+    let config = YamlConfig {
+        ecosystems,
+        ..Default::default()
+    };
+
+    let report = Runner::new(config, vec![], vec![], false)
+        .with_timeout(Duration::from_secs(30))
+        .run();
+    let commands = &report.tool("slow-tool").unwrap().commands;
+    // The tool timeout beats the global one, the command timeout beats the tool one
+    assert_eq!(
+        commands[0].outcome,
+        CommandOutcome::TimedOut(Duration::from_millis(200))
+    );
+    assert_eq!(
+        commands[1].outcome,
+        CommandOutcome::TimedOut(Duration::from_millis(100))
+    );
+}
+
+#[test]
+fn test_dry_run_runs_no_commands() {
+    use stop_nagging::report::CommandOutcome;
+
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let marker = temp_dir.path().join("ran");
+    let mut ecosystems = IndexMap::new();
+    let tool = Tool {
+        name: "marker-tool".to_string(),
+        executable: "echo".to_string(),
+        commands: vec![vec!["touch".to_string(), marker.to_string_lossy().to_string()].into()],
+        ..Default::default()
+    };
+    ecosystems.insert(
+        "test-ecosystem".to_string(),
+        Ecosystem {
+            check_ecosystem: None,
+            tools: vec![tool],
+            ..Default::default()
+        },
+    );
+    let config = YamlConfig {
+        ecosystems,
+        ..Default::default()
+    };
+
+    let report = Runner::new(config, vec![], vec![], false)
+        .with_dry_run(true)
+        .run();
+    let commands = &report.tool("marker-tool").unwrap().commands;
+    assert_eq!(commands[0].id, "marker-tool.command-1");
+    assert_eq!(commands[0].outcome, CommandOutcome::NotRun);
+    assert!(!marker.exists());
+}
+
+fn project_tool() -> YamlConfig {
+    use stop_nagging::yaml_config::{ActionScope, ToolCommand};
+
+    let mut ecosystems = IndexMap::new();
+    let tool = Tool {
+        name: "project-tool".to_string(),
+        executable: "echo".to_string(),
+        commands: vec![ToolCommand {
+            scope: Some(ActionScope::Project),
+            ..ToolCommand::from(vec!["touch".to_string(), "project-file".to_string()])
+        }],
+        ..Default::default()
+    };
+    ecosystems.insert(
+        "test-ecosystem".to_string(),
+        Ecosystem {
+            check_ecosystem: None,
+            tools: vec![tool],
+            ..Default::default()
+        },
+    );
+    YamlConfig {
+        ecosystems,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_project_command_runs_in_project_root() {
+    use stop_nagging::report::CommandOutcome;
+
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let report = Runner::new(project_tool(), vec![], vec![], false)
+        .with_project_root(Some(temp_dir.path().to_path_buf()))
+        .run();
+    let commands = &report.tool("project-tool").unwrap().commands;
+    assert_eq!(commands[0].outcome, CommandOutcome::Success);
+    assert!(temp_dir.path().join("project-file").exists());
+}
+
+#[test]
+fn test_project_command_without_root_is_not_run() {
+    use stop_nagging::report::CommandOutcome;
+
+    let report = Runner::new(project_tool(), vec![], vec![], false).run();
+    let tool = report.tool("project-tool").unwrap();
+    assert_eq!(tool.commands[0].outcome, CommandOutcome::NotRun);
+    assert!(tool.messages[0].contains("no project root"));
+}
+
+#[test]
+fn test_tool_not_detected_in_project() {
+    use stop_nagging::report::ToolStatus;
+    use stop_nagging::yaml_config::Detect;
+
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    std::fs::write(temp_dir.path().join("package.json"), "{}").unwrap();
+
+    let tool = |name: &str, file: &str| Tool {
+        name: name.to_string(),
+        executable: "echo".to_string(),
+        detect: Some(Detect {
+            files: vec![file.to_string()],
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    let mut ecosystems = IndexMap::new();
+    ecosystems.insert(
+        "test-ecosystem".to_string(),
+        Ecosystem {
+            tools: vec![
+                tool("node-tool", "package.json"),
+                tool("ruby-tool", "Gemfile"),
+            ],
+            ..Default::default()
+        },
+    );
+    let config = YamlConfig {
+        ecosystems,
+        ..Default::default()
+    };
+
+    let report = Runner::new(config, vec![], vec![], false)
+        .with_project_root(Some(temp_dir.path().to_path_buf()))
+        .run();
+    assert_eq!(
+        report.tool("node-tool").unwrap().status,
+        ToolStatus::Applied
+    );
+    assert_eq!(
+        report.tool("ruby-tool").unwrap().status,
+        ToolStatus::NotAvailable
+    );
+}
+
+#[test]
+fn test_tool_applies_in_nearest_detected_dir() {
+    use stop_nagging::report::{CommandOutcome, ToolStatus};
+    use stop_nagging::yaml_config::{ActionScope, Detect, ToolCommand};
+
+    // A monorepo whose JavaScript app lives in `apps/web`
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let root = temp_dir.path();
+    let app = root.join("apps").join("web");
+    std::fs::create_dir_all(app.join("src")).unwrap();
+    std::fs::write(app.join("package.json"), "{}").unwrap();
+
+    let mut config = project_tool();
+    let tool = &mut config.ecosystems[0].tools[0];
+    tool.detect = Some(Detect {
+        files: vec!["package.json".to_string()],
+        ..Default::default()
+    });
+    tool.commands = vec![ToolCommand {
+        scope: Some(ActionScope::Project),
+        ..ToolCommand::from(vec!["touch".to_string(), "project-file".to_string()])
+    }];
+
+    let report = Runner::new(config, vec![], vec![], false)
+        .with_project_root(Some(root.to_path_buf()))
+        .with_start_dir(Some(app.join("src")))
+        .run();
+    let tool = report.tool("project-tool").unwrap();
+    assert_eq!(tool.status, ToolStatus::Applied);
+    assert_eq!(tool.commands[0].outcome, CommandOutcome::Success);
+    assert_eq!(tool.project, None);
+    assert!(app.join("project-file").exists());
+}
+
+#[test]
+fn test_project_commands_run_in_every_workspace_member() {
+    use stop_nagging::report::CommandOutcome;
+    use stop_nagging::yaml_config::{ActionScope, ToolCommand};
+
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let root = temp_dir.path();
+    let members = vec![root.join("a"), root.join("b")];
+    for member in &members {
+        std::fs::create_dir(member).unwrap();
+    }
+    let global_log = root.join("global.log");
+
+    let tool = Tool {
+        name: "mixed-tool".to_string(),
+        executable: "echo".to_string(),
+        commands: vec![
+            ToolCommand {
+                scope: Some(ActionScope::Project),
+                ..ToolCommand::from(vec!["touch".to_string(), "project-file".to_string()])
+            },
+            ToolCommand::from(format!("echo run >> '{}'", global_log.display())),
+        ],
+        ..Default::default()
+    };
+    let mut ecosystems = IndexMap::new();
+    ecosystems.insert(
+        "test-ecosystem".to_string(),
+        Ecosystem {
+            tools: vec![tool],
+            ..Default::default()
+        },
+    );
+    let config = YamlConfig {
+        ecosystems,
+        ..Default::default()
+    };
+
+    let report = Runner::new(config, vec![], vec![], false)
+        .with_project_root(Some(root.to_path_buf()))
+        .with_workspace_members(members.clone())
+        .run();
+
+    assert!(root.join("project-file").exists());
+    for member in &members {
+        assert!(member.join("project-file").exists());
+    }
+    // Global commands run once, not once per member
+    assert_eq!(std::fs::read_to_string(&global_log).unwrap(), "run\n");
+
+    let projects: Vec<_> = report.tools.iter().map(|t| t.project.clone()).collect();
+    assert_eq!(
+        projects,
+        vec![None, Some(members[0].clone()), Some(members[1].clone())]
+    );
+    assert!(report.tools.iter().all(|t| t
+        .commands
+        .iter()
+        .all(|c| c.outcome == CommandOutcome::Success)));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_commands_run_for_each_installed_toolchain() {
+    use std::os::unix::fs::PermissionsExt;
+    use stop_nagging::report::CommandOutcome;
+    use stop_nagging::toolchains::Toolchain;
+
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let bin_dir = temp_dir.path().join("v18").join("bin");
+    std::fs::create_dir_all(&bin_dir).unwrap();
+    let log = temp_dir.path().join("calls.log");
+    // A tool that only exists inside the toolchain, so it must be found via the toolchain's PATH
+    let script = bin_dir.join("fake-npm");
+    std::fs::write(
+        &script,
+        format!("#!/bin/sh\necho \"$0 $*\" >> '{}'\n", log.display()),
+    )
+    .unwrap();
+    std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+    let tool = Tool {
+        name: "fake-npm".to_string(),
+        executable: "fake-npm".to_string(),
+        commands: vec![vec!["fake-npm".to_string(), "config".to_string()].into()],
+        ..Default::default()
+    };
+    let mut ecosystems = IndexMap::new();
+    ecosystems.insert(
+        "test-ecosystem".to_string(),
+        Ecosystem {
+            tools: vec![tool],
+            ..Default::default()
+        },
+    );
+    let config = YamlConfig {
+        ecosystems,
+        ..Default::default()
+    };
+    let toolchain = Toolchain {
+        manager: "nvm".to_string(),
+        language: "node".to_string(),
+        version: "v18".to_string(),
+        bin_dir: bin_dir.clone(),
+    };
+
+    let report = Runner::new(config, vec![], vec![], false)
+        .with_toolchains(vec![toolchain.clone()])
+        .run();
+
+    let applied: Vec<_> = report
+        .tools
+        .iter()
+        .filter(|t| t.tool == "fake-npm" && t.toolchain.as_ref() == Some(&toolchain))
+        .collect();
+    assert_eq!(applied.len(), 1);
+    assert_eq!(applied[0].commands[0].outcome, CommandOutcome::Success);
+    let calls = std::fs::read_to_string(&log).unwrap();
+    assert_eq!(calls.trim(), format!("{} config", script.display()));
+}
+
+#[test]
+fn test_system_mode_runs_system_variants_only() {
+    use stop_nagging::report::CommandOutcome;
+    use stop_nagging::yaml_config::{ActionScope, ToolCommand};
+
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let log = temp_dir.path().join("calls.log");
+    let append = |text: &str| format!("echo {} >> '{}'", text, log.display());
+
+    let tool = Tool {
+        name: "system-tool".to_string(),
+        executable: "echo".to_string(),
+        commands: vec![
+            ToolCommand {
+                id: Some("with-variant".to_string()),
+                system: Some(append("system").into()),
+                ..ToolCommand::from(append("user"))
+            },
+            ToolCommand {
+                id: Some("user-only".to_string()),
+                ..ToolCommand::from(append("user-only"))
+            },
+            ToolCommand {
+                id: Some("project".to_string()),
+                scope: Some(ActionScope::Project),
+                ..ToolCommand::from(append("project"))
+            },
+        ],
+        ..Default::default()
+    };
+    let mut ecosystems = IndexMap::new();
+    ecosystems.insert(
+        "test-ecosystem".to_string(),
+        Ecosystem {
+            tools: vec![tool],
+            ..Default::default()
+        },
+    );
+    let config = YamlConfig {
+        ecosystems,
+        ..Default::default()
+    };
+
+    let report = Runner::new(config, vec![], vec![], false)
+        .with_system(true)
+        .with_project_root(Some(temp_dir.path().to_path_buf()))
+        .run();
+
+    let commands = &report.tool("system-tool").unwrap().commands;
+    assert_eq!(commands[0].outcome, CommandOutcome::Success);
+    assert_eq!(commands[1].outcome, CommandOutcome::NotRun);
+    assert_eq!(commands[2].outcome, CommandOutcome::Success);
+    let calls = std::fs::read_to_string(&log).unwrap();
+    assert_eq!(calls, "system\nproject\n");
+}
+
+#[test]
+fn test_system_mode_under_root_skips_variants_it_cant_redirect() {
+    use stop_nagging::paths::Paths;
+    use stop_nagging::report::CommandOutcome;
+    use stop_nagging::yaml_config::ToolCommand;
+
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let log = temp_dir.path().join("calls.log");
+    let tool = Tool {
+        name: "system-tool".to_string(),
+        executable: "echo".to_string(),
+        commands: vec![ToolCommand {
+            system: Some(format!("echo system >> '{}'", log.display()).into()),
+            ..ToolCommand::from("true")
+        }],
+        ..Default::default()
+    };
+    let mut ecosystems = IndexMap::new();
+    ecosystems.insert(
+        "test-ecosystem".to_string(),
+        Ecosystem {
+            tools: vec![tool],
+            ..Default::default()
+        },
+    );
+    let config = YamlConfig {
+        ecosystems,
+        ..Default::default()
+    };
+
+    let report = Runner::new(config, vec![], vec![], false)
+        .with_system(true)
+        .with_paths(Paths {
+            home: Some(temp_dir.path().join("home")),
+            root: Some(temp_dir.path().to_path_buf()),
+        })
+        .run();
+
+    let commands = &report.tool("system-tool").unwrap().commands;
+    assert_eq!(commands[0].outcome, CommandOutcome::NotRun);
+    let messages = &report.tool("system-tool").unwrap().messages;
+    assert!(
+        messages.iter().any(|m| m.contains("--root")),
+        "{:?}",
+        messages
+    );
+    assert!(!log.exists());
+}
+
+fn logging_tool(log: &std::path::Path, command: &str) -> YamlConfig {
+    let tool = Tool {
+        name: "logging-tool".to_string(),
+        executable: "echo".to_string(),
+        commands: vec![format!("echo run >> '{}'; {}", log.display(), command).into()],
+        ..Default::default()
+    };
+    let mut ecosystems = IndexMap::new();
+    ecosystems.insert(
+        "test-ecosystem".to_string(),
+        Ecosystem {
+            tools: vec![tool],
+            ..Default::default()
+        },
+    );
+    YamlConfig {
+        ecosystems,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_cached_tools_are_skipped_until_forced() {
+    use stop_nagging::cache::{self, Cache};
+    use stop_nagging::report::ToolStatus;
+
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let log = temp_dir.path().join("calls.log");
+    let cache_file = cache::file_in(temp_dir.path());
+    let run = |force: bool| {
+        Runner::new(logging_tool(&log, "true"), vec![], vec![], false)
+            .with_cache(Cache::load(&cache_file))
+            .with_force(force)
+            .run()
+    };
+
+    assert_eq!(
+        run(false).tool("logging-tool").unwrap().status,
+        ToolStatus::Applied
+    );
+    assert_eq!(
+        run(false).tool("logging-tool").unwrap().status,
+        ToolStatus::UpToDate
+    );
+    assert_eq!(std::fs::read_to_string(&log).unwrap(), "run\n");
+
+    assert_eq!(
+        run(true).tool("logging-tool").unwrap().status,
+        ToolStatus::Applied
+    );
+    assert_eq!(std::fs::read_to_string(&log).unwrap(), "run\nrun\n");
+}
+
+#[test]
+fn test_failed_tools_are_not_cached() {
+    use stop_nagging::cache::{self, Cache};
+
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let log = temp_dir.path().join("calls.log");
+    let cache_file = cache::file_in(temp_dir.path());
+    for _ in 0..2 {
+        Runner::new(logging_tool(&log, "false"), vec![], vec![], false)
+            .with_cache(Cache::load(&cache_file))
+            .run();
+    }
+    assert_eq!(std::fs::read_to_string(&log).unwrap(), "run\nrun\n");
+}
+
+#[test]
+fn test_ordering_cycle_is_reported_not_printed() {
+    let yaml = r#"
+ecosystems:
+  test:
+    tools:
+      - name: a
+        executable: sh
+        after: [b]
+      - name: b
+        executable: sh
+        after: [a]
+"#;
+    let config: YamlConfig = serde_yaml::from_str(yaml).unwrap();
+    let report = Runner::new(config, vec![], vec![], false)
+        .with_dry_run(true)
+        .run();
+
+    assert_eq!(report.tools.len(), 2);
+    assert_eq!(report.messages.len(), 1);
+    assert!(
+        report.messages[0].contains("cycle"),
+        "{:?}",
+        report.messages
+    );
+}
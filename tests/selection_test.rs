@@ -0,0 +1,199 @@
+use indexmap::IndexMap;
+use stop_nagging::selection::Selection;
+use stop_nagging::yaml_config::{
+    ActionKind, ActionScope, Ecosystem, Profile, Tool, ToolCommand, YamlConfig,
+};
+
+fn config() -> YamlConfig {
+    let mut env = IndexMap::new();
+    env.insert("YARN_IGNORE_PATH".to_string(), "1".into());
+    let yarn = Tool {
+        name: "yarn".to_string(),
+        executable: "yarn".to_string(),
+        env,
+        kind: Some(ActionKind::Telemetry),
+        commands: vec![
+            ToolCommand {
+                id: Some("enableTelemetry".to_string()),
+                ..ToolCommand::from("yarn config set enableTelemetry 0")
+            },
+            ToolCommand {
+                kind: Some(ActionKind::Behavior),
+                ..ToolCommand::from("yarn config set enableGlobalCache 1")
+            },
+            "yarn config set enableProgressBars 0".into(),
+        ],
+        ..Default::default()
+    };
+    let aws = Tool {
+        name: "aws".to_string(),
+        executable: "aws".to_string(),
+        ..Default::default()
+    };
+    let mut ecosystems = IndexMap::new();
+    ecosystems.insert(
+        "test".to_string(),
+        Ecosystem {
+            check_ecosystem: None,
+            tools: vec![yarn, aws],
+            ..Default::default()
+        },
+    );
+    YamlConfig {
+        ecosystems,
+        ..Default::default()
+    }
+}
+
+fn tools(config: &YamlConfig) -> &[Tool] {
+    &config.ecosystems["test"].tools
+}
+
+#[test]
+fn test_action_ids() {
+    let config = config();
+    assert_eq!(
+        tools(&config)[0].action_ids(),
+        vec![
+            "yarn.YARN_IGNORE_PATH",
+            "yarn.enableTelemetry",
+            "yarn.command-2",
+            "yarn.command-3",
+        ]
+    );
+}
+
+#[test]
+fn test_skip_actions() {
+    let selection = Selection {
+        skip_actions: vec![
+            "yarn.YARN_IGNORE_PATH".to_string(),
+            "yarn.command-2".to_string(),
+        ],
+        ..Default::default()
+    };
+    let selected = selection.apply(&config());
+    let yarn = &tools(&selected)[0];
+    assert!(yarn.env.is_empty());
+    // Default IDs don't shift when an earlier command is skipped
+    assert_eq!(
+        yarn.action_ids(),
+        vec!["yarn.enableTelemetry", "yarn.command-3"]
+    );
+    assert_eq!(tools(&selected).len(), 2);
+}
+
+#[test]
+fn test_only_actions() {
+    let selection = Selection {
+        only_actions: vec!["yarn.enableTelemetry".to_string()],
+        ..Default::default()
+    };
+    let selected = selection.apply(&config());
+    assert_eq!(tools(&selected).len(), 1);
+    assert_eq!(
+        tools(&selected)[0].action_ids(),
+        vec!["yarn.enableTelemetry"]
+    );
+}
+
+#[test]
+fn test_tool_without_remaining_actions_is_dropped() {
+    let selection = Selection {
+        skip_actions: config().ecosystems["test"].tools[0].action_ids(),
+        ..Default::default()
+    };
+    let selected = selection.apply(&config());
+    let names: Vec<&str> = tools(&selected).iter().map(|t| t.name.as_str()).collect();
+    assert_eq!(names, vec!["aws"]);
+}
+
+#[test]
+fn test_unknown_ids() {
+    let selection = Selection {
+        skip_actions: vec!["yarn.enableTelemetry".to_string(), "yarn.nope".to_string()],
+        only_actions: vec!["npm.nope".to_string()],
+        ..Default::default()
+    };
+    assert_eq!(
+        selection.unknown_ids(&config()),
+        vec!["yarn.nope", "npm.nope"]
+    );
+}
+
+#[test]
+fn test_behavior_actions_are_opt_in() {
+    let selected = Selection::default().apply(&config());
+    assert_eq!(
+        tools(&selected)[0].action_ids(),
+        vec![
+            "yarn.YARN_IGNORE_PATH",
+            "yarn.enableTelemetry",
+            "yarn.command-3"
+        ]
+    );
+
+    let selection = Selection {
+        kinds: vec![ActionKind::Behavior],
+        ..Default::default()
+    };
+    let selected = selection.apply(&config());
+    assert_eq!(tools(&selected).len(), 1);
+    assert_eq!(tools(&selected)[0].action_ids(), vec!["yarn.command-2"]);
+}
+
+#[test]
+fn test_only_actions_opts_into_behavior() {
+    let selection = Selection {
+        only_actions: vec!["yarn.command-2".to_string()],
+        ..Default::default()
+    };
+    let selected = selection.apply(&config());
+    assert_eq!(tools(&selected)[0].action_ids(), vec!["yarn.command-2"]);
+}
+
+#[test]
+fn test_profile_tools_and_overrides() {
+    let mut overrides = IndexMap::new();
+    overrides.insert("yarn.command-2".to_string(), true);
+    overrides.insert("yarn.enableTelemetry".to_string(), false);
+    let profile = Profile {
+        tools: vec!["yarn".to_string()],
+        overrides,
+        ..Default::default()
+    };
+    let selected = Selection::default().with_profile(&profile).apply(&config());
+    assert_eq!(tools(&selected).len(), 1);
+    assert_eq!(
+        tools(&selected)[0].action_ids(),
+        vec!["yarn.YARN_IGNORE_PATH", "yarn.command-2", "yarn.command-3"]
+    );
+}
+
+#[test]
+fn test_scope() {
+    let mut config = config();
+    let yarn = &mut config.ecosystems["test"].tools[0];
+    yarn.commands[0].scope = Some(ActionScope::Project);
+
+    let selection = Selection {
+        scope: Some(ActionScope::Project),
+        ..Default::default()
+    };
+    let selected = selection.apply(&config);
+    assert_eq!(tools(&selected).len(), 1);
+    assert_eq!(
+        tools(&selected)[0].action_ids(),
+        vec!["yarn.enableTelemetry"]
+    );
+
+    let selection = Selection {
+        scope: Some(ActionScope::Global),
+        ..Default::default()
+    };
+    let selected = selection.apply(&config);
+    assert_eq!(
+        tools(&selected)[0].action_ids(),
+        vec!["yarn.YARN_IGNORE_PATH", "yarn.command-3"]
+    );
+}
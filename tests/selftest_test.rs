@@ -0,0 +1,126 @@
+#![cfg(unix)]
+
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use stop_nagging::selftest::{self, SelftestOptions, Verdict};
+use stop_nagging::yaml_config::YamlConfig;
+use tempfile::TempDir;
+
+/// A stand-in that nags unless its env var or its config file turns that off.
+const FAKE_TOOL: &str = r#"#!/bin/sh
+echo "fake-nagger 1.0"
+if [ "$FAKE_NO_NAG" != "1" ] && ! grep -qs 'nag=false' "$HOME/.fakerc"; then
+  echo "fake-nagger: update available, run fake-nagger upgrade" >&2
+fi
+"#;
+
+fn write_fake_tool(dir: &Path) -> String {
+    let path = dir.join("fake-nagger");
+    std::fs::write(&path, FAKE_TOOL).unwrap();
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    format!(
+        r#"cp '{}' "$STOP_NAGGING_PREFIX/bin/fake-nagger""#,
+        path.display()
+    )
+}
+
+fn config(tool: &str) -> YamlConfig {
+    let yaml = format!(
+        r#"
+ecosystems:
+  test:
+    tools:
+      - name: "fake"
+        executable: "fake-nagger"
+        kind: update_nag
+        nag_patterns: ["update available"]
+        nag_probe: ["fake-nagger", "--version"]
+{}
+"#,
+        tool
+    );
+    serde_yaml::from_str(&yaml).unwrap()
+}
+
+fn run(config: &YamlConfig, installer: Option<String>) -> selftest::SelftestResult {
+    let options = SelftestOptions {
+        installer,
+        ..Default::default()
+    };
+    let mut results = selftest::run(config, &[], &options);
+    assert_eq!(results.len(), 1);
+    results.remove(0)
+}
+
+#[test]
+fn test_selftest_passes_when_env_silences_nag() {
+    let temp_dir = TempDir::new().unwrap();
+    let installer = write_fake_tool(temp_dir.path());
+    let config = config("        env:\n          FAKE_NO_NAG: \"1\"");
+
+    let result = run(&config, Some(installer));
+    assert_eq!(result.verdict, Verdict::Passed, "{}", result);
+    assert_eq!(
+        result.before,
+        vec!["fake-nagger: update available, run fake-nagger upgrade"]
+    );
+    assert!(result.after.is_empty());
+}
+
+#[test]
+fn test_selftest_commands_write_to_sandbox_home() {
+    let temp_dir = TempDir::new().unwrap();
+    let installer = write_fake_tool(temp_dir.path());
+    let config = config("        commands:\n          - 'echo nag=false > \"$HOME/.fakerc\"'");
+
+    let result = run(&config, Some(installer));
+    assert_eq!(result.verdict, Verdict::Passed, "{}", result);
+}
+
+#[test]
+fn test_selftest_fails_when_nag_remains() {
+    let temp_dir = TempDir::new().unwrap();
+    let installer = write_fake_tool(temp_dir.path());
+    let config = config("        env:\n          UNRELATED: \"1\"");
+
+    let result = run(&config, Some(installer));
+    assert!(matches!(result.verdict, Verdict::Failed(_)), "{}", result);
+    assert_eq!(result.after.len(), 1);
+}
+
+#[test]
+fn test_selftest_skips_when_probe_never_nags() {
+    let temp_dir = TempDir::new().unwrap();
+    let installer = write_fake_tool(temp_dir.path());
+    let mut config = config("        env:\n          FAKE_NO_NAG: \"1\"");
+    let tool = &mut config.ecosystems.get_mut("test").unwrap().tools[0];
+    tool.nag_patterns = vec!["never printed".to_string()];
+
+    let result = run(&config, Some(installer));
+    assert!(matches!(result.verdict, Verdict::Skipped(_)), "{}", result);
+}
+
+#[test]
+fn test_selftest_fails_when_installer_installs_nothing() {
+    let config = config("        env:\n          FAKE_NO_NAG: \"1\"");
+
+    let result = run(&config, Some("true".to_string()));
+    assert!(matches!(result.verdict, Verdict::Failed(_)), "{}", result);
+}
+
+#[test]
+fn test_selftest_only_runs_named_tools() {
+    let config = config("");
+    let options = SelftestOptions::default();
+    assert!(selftest::run(&config, &["other".to_string()], &options).is_empty());
+}
+
+#[test]
+fn test_default_config_probes_declare_patterns() {
+    let config = YamlConfig::from_default().unwrap();
+    for tool in config.ecosystems.values().flat_map(|eco| &eco.tools) {
+        if tool.nag_probe.is_some() {
+            assert!(!tool.nag_patterns.is_empty(), "{}", tool.name);
+        }
+    }
+}
@@ -0,0 +1,120 @@
+#![cfg(unix)]
+
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use std::process::Command;
+use stop_nagging::shims;
+use tempfile::TempDir;
+
+fn write_executable(path: &Path, contents: &str) {
+    fs::write(path, contents).unwrap();
+    fs::set_permissions(path, fs::Permissions::from_mode(0o755)).unwrap();
+}
+
+fn run_with_path(path: &str) -> std::process::Output {
+    // Guard against a shim that keeps running itself
+    Command::new("timeout")
+        .args(["5", "sh", "-c", "fake-tool --flag"])
+        .env("PATH", path)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn test_shim_sets_env_and_runs_real_tool() {
+    let temp_dir = TempDir::new().unwrap();
+    let shim_dir = temp_dir.path().join("shims");
+    let real_dir = temp_dir.path().join("bin");
+    fs::create_dir_all(&shim_dir).unwrap();
+    fs::create_dir_all(&real_dir).unwrap();
+    write_executable(
+        &real_dir.join("fake-tool"),
+        "#!/bin/sh\necho \"real $SHIM_VAR $*\"\n",
+    );
+    write_executable(
+        &shim_dir.join("fake-tool"),
+        &shims::render_shim("fake-tool", "export SHIM_VAR='quiet'\n", &shim_dir),
+    );
+
+    let path = format!(
+        "{}:{}:/usr/bin:/bin",
+        shim_dir.display(),
+        real_dir.display()
+    );
+    let output = run_with_path(&path);
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "real quiet --flag\n"
+    );
+}
+
+#[test]
+fn test_shim_does_not_recurse_into_itself() {
+    let temp_dir = TempDir::new().unwrap();
+    let shim_dir = temp_dir.path().join("shims");
+    fs::create_dir_all(&shim_dir).unwrap();
+    write_executable(
+        &shim_dir.join("fake-tool"),
+        &shims::render_shim("fake-tool", "", &shim_dir),
+    );
+    // The same shim reachable through a second directory on PATH
+    let link = temp_dir.path().join("link");
+    std::os::unix::fs::symlink(&shim_dir, &link).unwrap();
+
+    let path = format!("{}:{}:/usr/bin:/bin", shim_dir.display(), link.display());
+    let output = run_with_path(&path);
+    assert_eq!(output.status.code(), Some(127));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("fake-tool not found on PATH"));
+}
+
+#[test]
+fn test_remove_keeps_other_files() {
+    let temp_dir = TempDir::new().unwrap();
+    let dir = temp_dir.path();
+    write_executable(
+        &dir.join("fake-tool"),
+        &shims::render_shim("fake-tool", "", dir),
+    );
+    write_executable(&dir.join("other"), "#!/bin/sh\n");
+
+    assert_eq!(shims::list(dir).unwrap(), vec![dir.join("fake-tool")]);
+    assert_eq!(shims::remove(dir).unwrap(), vec![dir.join("fake-tool")]);
+    assert!(!dir.join("fake-tool").exists());
+    assert!(dir.join("other").exists());
+}
+
+#[test]
+fn test_install_skips_files_that_are_not_shims() {
+    use stop_nagging::runner::Runner;
+    use stop_nagging::yaml_config::YamlConfig;
+
+    let temp_dir = TempDir::new().unwrap();
+    let dir = temp_dir.path();
+    write_executable(&dir.join("sh"), "#!/bin/sh\necho mine\n");
+    let yaml = r#"
+ecosystems:
+  test:
+    tools:
+      - name: shell
+        executable: sh
+        env:
+          SHIM_TEST: "1"
+"#;
+    let config: YamlConfig = serde_yaml::from_str(yaml).unwrap();
+    let report = Runner::new(config.clone(), vec![], vec![], false)
+        .with_dry_run(true)
+        .run();
+
+    let installed = shims::install(dir, &config, &report).unwrap();
+    assert!(installed.written.is_empty());
+    assert_eq!(
+        installed.skipped,
+        vec![dir.canonicalize().unwrap().join("sh")]
+    );
+    assert_eq!(
+        fs::read_to_string(dir.join("sh")).unwrap(),
+        "#!/bin/sh\necho mine\n"
+    );
+}
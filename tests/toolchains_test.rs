@@ -0,0 +1,71 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use stop_nagging::toolchains::discover;
+use tempfile::TempDir;
+
+fn mkdirs(root: &Path, dirs: &[&str]) {
+    for dir in dirs {
+        fs::create_dir_all(root.join(dir)).unwrap();
+    }
+}
+
+#[test]
+fn test_discovers_version_manager_installs() {
+    let temp_dir = TempDir::new().unwrap();
+    let home = temp_dir.path();
+    mkdirs(
+        home,
+        &[
+            ".nvm/versions/node/v18.19.0/bin",
+            ".nvm/versions/node/v20.11.0/bin",
+            ".pyenv/versions/3.12.1/bin",
+            ".asdf/installs/nodejs/21.6.0/bin",
+            ".local/share/mise/installs/python/3.11.7/bin",
+        ],
+    );
+
+    let found: Vec<String> = discover(home, &HashMap::new())
+        .iter()
+        .map(|t| t.to_string())
+        .collect();
+    assert_eq!(
+        found,
+        vec![
+            "node v18.19.0 (nvm)",
+            "node v20.11.0 (nvm)",
+            "python 3.12.1 (pyenv)",
+            "nodejs 21.6.0 (asdf)",
+            "python 3.11.7 (mise)",
+        ]
+    );
+}
+
+#[test]
+fn test_root_env_var_overrides_default() {
+    let temp_dir = TempDir::new().unwrap();
+    let home = temp_dir.path().join("home");
+    let nvm_dir = temp_dir.path().join("nvm");
+    mkdirs(&home, &[".nvm/versions/node/v16.0.0/bin"]);
+    mkdirs(&nvm_dir, &["versions/node/v22.0.0/bin"]);
+
+    let vars = HashMap::from([("NVM_DIR".to_string(), nvm_dir.to_string_lossy().to_string())]);
+    let found = discover(&home, &vars);
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].version, "v22.0.0");
+    assert_eq!(found[0].bin_dir, nvm_dir.join("versions/node/v22.0.0/bin"));
+}
+
+#[test]
+fn test_versions_without_bin_dir_are_skipped() {
+    let temp_dir = TempDir::new().unwrap();
+    let home = temp_dir.path();
+    mkdirs(
+        home,
+        &[".pyenv/versions/broken", ".rustup/toolchains/stable/bin"],
+    );
+
+    let found = discover(home, &HashMap::new());
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].language, "rust");
+}
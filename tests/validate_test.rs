@@ -0,0 +1,163 @@
+use stop_nagging::validate::{check, validate, Severity};
+use stop_nagging::yaml_config::YamlConfig;
+use stop_nagging::Error;
+
+#[test]
+fn test_default_config_is_valid() {
+    let config = YamlConfig::from_default().unwrap();
+    let issues = validate(&config);
+    assert!(issues.is_empty(), "unexpected issues: {:?}", issues);
+}
+
+#[test]
+fn test_env_conflict_with_different_values() {
+    let yaml = r#"
+ecosystems:
+  first:
+    tools:
+      - name: first-tool
+        executable: first
+        kind: telemetry
+        env:
+          SHARED_VAR: "1"
+  second:
+    tools:
+      - name: second-tool
+        executable: second
+        kind: telemetry
+        env:
+          SHARED_VAR: "0"
+"#;
+    let config: YamlConfig = serde_yaml::from_str(yaml).unwrap();
+    let issues = validate(&config);
+    assert_eq!(issues.len(), 1);
+    assert_eq!(issues[0].severity, Severity::Warning);
+    assert!(issues[0].message.contains("SHARED_VAR"));
+    assert!(issues[0].message.contains("first-tool"));
+    assert!(issues[0].message.contains("second-tool"));
+}
+
+#[test]
+fn test_same_value_is_not_a_conflict() {
+    let yaml = r#"
+ecosystems:
+  test:
+    tools:
+      - name: first-tool
+        executable: first
+        kind: telemetry
+        env:
+          SHARED_VAR: "1"
+      - name: second-tool
+        executable: second
+        kind: telemetry
+        env:
+          SHARED_VAR: "1"
+"#;
+    let config: YamlConfig = serde_yaml::from_str(yaml).unwrap();
+    assert!(validate(&config).is_empty());
+}
+
+#[test]
+fn test_plain_shell_command_suggests_argv() {
+    let yaml = r#"
+ecosystems:
+  test:
+    tools:
+      - name: test-tool
+        executable: test
+        kind: telemetry
+        commands:
+          - "npm config set update-notifier false"
+          - "echo done > out.txt"
+          - ["npm", "config", "set", "fund", "false"]
+"#;
+    let config: YamlConfig = serde_yaml::from_str(yaml).unwrap();
+    let issues = validate(&config);
+    assert_eq!(issues.len(), 1, "unexpected issues: {:?}", issues);
+    assert_eq!(issues[0].severity, Severity::Warning);
+    assert!(issues[0].message.contains("update-notifier"));
+}
+
+#[test]
+fn test_empty_argv_is_an_error() {
+    let yaml = r#"
+ecosystems:
+  test:
+    tools:
+      - name: test-tool
+        executable: test
+        kind: telemetry
+        commands:
+          - []
+"#;
+    let config: YamlConfig = serde_yaml::from_str(yaml).unwrap();
+    let issues = validate(&config);
+    assert_eq!(issues.len(), 1);
+    assert_eq!(issues[0].severity, Severity::Error);
+}
+
+#[test]
+fn test_duplicate_action_ids() {
+    let yaml = r#"
+ecosystems:
+  test:
+    tools:
+      - name: test-tool
+        executable: test
+        kind: telemetry
+        commands:
+          - id: same
+            run: ["true"]
+          - id: same
+            run: ["false"]
+"#;
+    let config: YamlConfig = serde_yaml::from_str(yaml).unwrap();
+    let issues = validate(&config);
+    assert_eq!(issues.len(), 1);
+    assert_eq!(issues[0].severity, Severity::Error);
+    assert!(issues[0].message.contains("test-tool.same"));
+}
+
+#[test]
+fn test_action_without_kind() {
+    let yaml = r#"
+ecosystems:
+  test:
+    tools:
+      - name: test-tool
+        executable: test
+        env:
+          TEST_VAR: "1"
+          KINDED_VAR:
+            value: "1"
+            kind: prompt
+"#;
+    let config: YamlConfig = serde_yaml::from_str(yaml).unwrap();
+    let issues = validate(&config);
+    assert_eq!(issues.len(), 1);
+    assert_eq!(issues[0].severity, Severity::Error);
+    assert!(issues[0].message.contains("test-tool.TEST_VAR"));
+    assert!(matches!(check(&config), Err(Error::Invalid { .. })));
+}
+
+#[test]
+fn test_profile_with_unknown_tool_and_action() {
+    let yaml = r#"
+profiles:
+  ci:
+    tools: [missing-tool]
+    overrides:
+      test-tool.MISSING: true
+ecosystems:
+  test:
+    tools:
+      - name: test-tool
+        executable: test
+"#;
+    let config: YamlConfig = serde_yaml::from_str(yaml).unwrap();
+    let issues = validate(&config);
+    assert_eq!(issues.len(), 2);
+    assert!(issues[0].message.contains("missing-tool"));
+    assert!(issues[1].message.contains("test-tool.MISSING"));
+}
@@ -0,0 +1,125 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use stop_nagging::workspace::members;
+use tempfile::TempDir;
+
+fn mkdirs(root: &Path, dirs: &[&str]) {
+    for dir in dirs {
+        fs::create_dir_all(root.join(dir)).unwrap();
+    }
+}
+
+fn relative(root: &Path, members: Vec<PathBuf>) -> Vec<String> {
+    members
+        .iter()
+        .map(|m| {
+            m.strip_prefix(root)
+                .unwrap()
+                .to_string_lossy()
+                .replace('\\', "/")
+        })
+        .collect()
+}
+
+#[test]
+fn test_pnpm_workspace() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path();
+    mkdirs(
+        root,
+        &[
+            "packages/a",
+            "packages/b",
+            "packages/legacy",
+            "apps/web/site",
+        ],
+    );
+    fs::write(
+        root.join("pnpm-workspace.yaml"),
+        "packages:\n  - 'packages/*'\n  - 'apps/**'\n  - '!packages/legacy'\n",
+    )
+    .unwrap();
+
+    assert_eq!(
+        relative(root, members(root)),
+        vec![
+            "apps",
+            "apps/web",
+            "apps/web/site",
+            "packages/a",
+            "packages/b"
+        ]
+    );
+}
+
+#[test]
+fn test_package_json_workspaces() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path();
+    mkdirs(root, &["packages/pkg-a", "packages/other", "tools"]);
+    fs::write(
+        root.join("package.json"),
+        r#"{"workspaces": {"packages": ["packages/pkg-*", "tools"]}}"#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        relative(root, members(root)),
+        vec!["packages/pkg-a", "tools"]
+    );
+}
+
+#[test]
+fn test_wildcard_with_non_ascii_names() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path();
+    mkdirs(root, &["packages/pkg-éx", "packages/pkg-ü", "packages/ödd"]);
+    fs::write(
+        root.join("package.json"),
+        r#"{"workspaces": ["packages/pkg-*x", "packages/pkg-*"]}"#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        relative(root, members(root)),
+        vec!["packages/pkg-éx", "packages/pkg-ü"]
+    );
+}
+
+#[test]
+fn test_gradle_settings() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path();
+    mkdirs(root, &["app", "libs/core"]);
+    fs::write(
+        root.join("settings.gradle.kts"),
+        "rootProject.name = \"demo\"\ninclude(\":app\")\ninclude(\":libs:core\", \":missing\")\n",
+    )
+    .unwrap();
+
+    assert_eq!(relative(root, members(root)), vec!["app", "libs/core"]);
+}
+
+#[test]
+fn test_cargo_workspace() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path();
+    mkdirs(root, &["crates/one", "crates/two", "crates/experimental"]);
+    fs::write(
+        root.join("Cargo.toml"),
+        "[workspace]\nmembers = [\"crates/*\"]\nexclude = [\"crates/experimental\"]\n",
+    )
+    .unwrap();
+
+    assert_eq!(
+        relative(root, members(root)),
+        vec!["crates/one", "crates/two"]
+    );
+}
+
+#[test]
+fn test_not_a_workspace() {
+    let temp_dir = TempDir::new().unwrap();
+    fs::write(temp_dir.path().join("package.json"), r#"{"name": "app"}"#).unwrap();
+    assert!(members(temp_dir.path()).is_empty());
+}
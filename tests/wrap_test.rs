@@ -0,0 +1,185 @@
+use std::collections::VecDeque;
+use std::io::{self, Read, Write};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use stop_nagging::wrap::{NagFilter, MAX_PARTIAL_LINE};
+use stop_nagging::yaml_config::YamlConfig;
+
+fn filtered(filter: &NagFilter, input: &str) -> String {
+    let mut output = Vec::new();
+    filter.filter(input.as_bytes(), &mut output).unwrap();
+    String::from_utf8(output).unwrap()
+}
+
+#[test]
+fn test_filter_drops_matching_lines() {
+    let filter = NagFilter::new(&["^notice: new version"]);
+    let output = filtered(
+        &filter,
+        "building\nnotice: new version 2.0 available\ndone\n",
+    );
+    assert_eq!(output, "building\ndone\n");
+}
+
+#[test]
+fn test_filter_ignores_colors_when_matching() {
+    let filter = NagFilter::new(&["^notice: new version"]);
+    let output = filtered(
+        &filter,
+        "\x1b[33mnotice\x1b[0m: new version\n\x1b[32mok\x1b[0m\n",
+    );
+    assert_eq!(output, "\x1b[32mok\x1b[0m\n");
+}
+
+#[test]
+fn test_filter_keeps_crlf_and_unterminated_lines() {
+    let filter = NagFilter::new(&["^nag$"]);
+    assert_eq!(filtered(&filter, "nag\r\nkeep\r\n"), "keep\r\n");
+    assert_eq!(
+        filtered(&filter, "keep\nEnter name: "),
+        "keep\nEnter name: "
+    );
+}
+
+/// Hands out one chunk per read. Before a chunk, it waits until `output`
+/// contains the text it is paired with, or for a fixed delay.
+struct ChunkedReader {
+    chunks: VecDeque<(Option<&'static str>, &'static [u8])>,
+    delay: Duration,
+    output: Shared,
+}
+
+impl Read for ChunkedReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let Some((wait_for, chunk)) = self.chunks.pop_front() else {
+            return Ok(0);
+        };
+        match wait_for {
+            Some(text) => {
+                let start = Instant::now();
+                while !self.output.text().contains(text) {
+                    assert!(
+                        start.elapsed() < Duration::from_secs(5),
+                        "waited for {}",
+                        text
+                    );
+                    thread::sleep(Duration::from_millis(10));
+                }
+            }
+            None => thread::sleep(self.delay),
+        }
+        buf[..chunk.len()].copy_from_slice(chunk);
+        Ok(chunk.len())
+    }
+}
+
+#[derive(Clone, Default)]
+struct Shared(Arc<Mutex<Vec<u8>>>);
+
+impl Shared {
+    fn text(&self) -> String {
+        String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+    }
+}
+
+impl Write for Shared {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_filter_drops_nag_written_in_pieces() {
+    let filter = NagFilter::new(&["^npm notice New version"]);
+    let output = Shared::default();
+    let input = ChunkedReader {
+        chunks: VecDeque::from([
+            (None, &b"npm notice New "[..]),
+            (None, &b"version 2.0\nok"[..]),
+            (None, &b"\n"[..]),
+        ]),
+        delay: Duration::from_millis(200),
+        output: output.clone(),
+    };
+    filter.filter(input, output.clone()).unwrap();
+    assert_eq!(output.text(), "ok\n");
+}
+
+#[test]
+fn test_filter_passes_on_waiting_prompt() {
+    let filter = NagFilter::new(&["^nag"]);
+    let output = Shared::default();
+    // The answer only comes once the prompt is shown
+    let input = ChunkedReader {
+        chunks: VecDeque::from([
+            (None, &b"Enter name: "[..]),
+            (Some("Enter name: "), &b"alice\n"[..]),
+        ]),
+        delay: Duration::ZERO,
+        output: output.clone(),
+    };
+    filter.filter(input, output.clone()).unwrap();
+    assert_eq!(output.text(), "Enter name: alice\n");
+}
+
+#[test]
+fn test_filter_passes_on_long_partial_lines() {
+    let filter = NagFilter::new(&["^x"]);
+    let long = "x".repeat(MAX_PARTIAL_LINE);
+    assert_eq!(
+        filtered(&filter, &format!("{}\n", long)),
+        format!("{}\n", long)
+    );
+}
+
+#[test]
+fn test_filter_skips_invalid_patterns() {
+    let filter = NagFilter::new(&["(", "^nag"]);
+    assert!(filter.is_nag(b"nag\n"));
+    assert!(!filter.is_nag(b"("));
+}
+
+#[test]
+fn test_for_program_matches_executable_name() {
+    let yaml = r#"
+ecosystems:
+  test:
+    tools:
+      - name: "fake"
+        executable: "fake-npm"
+        kind: update_nag
+        nag_patterns: ["^update available"]
+      - name: "other"
+        executable: "other"
+        kind: update_nag
+        nag_patterns: ["^other"]
+"#;
+    let config: YamlConfig = serde_yaml::from_str(yaml).unwrap();
+    let filter = NagFilter::for_program(&config, "/usr/local/bin/fake-npm");
+    assert!(filter.is_nag(b"update available: 2.0\n"));
+    assert!(!filter.is_nag(b"other line\n"));
+    assert!(!NagFilter::for_program(&config, "unknown").is_nag(b"update available\n"));
+}
+
+#[test]
+fn test_default_config_filters_npm_banner() {
+    let config = YamlConfig::from_default().unwrap();
+    let filter = NagFilter::for_program(&config, "npm");
+    let banner = "npm notice\n\
+npm notice New major version of npm available! 9.8.1 -> 10.2.4\n\
+npm notice Changelog: https://github.com/npm/cli/releases/tag/v10.2.4\n\
+npm notice Run npm install -g npm@10.2.4 to update!\n\
+npm notice\n";
+    let output = filtered(&filter, &format!("added 1 package\n{}", banner));
+    assert!(output.starts_with("added 1 package\n"));
+    assert!(!output.contains("New major version"));
+
+    let pip = NagFilter::for_program(&config, "pip");
+    assert!(pip.is_nag(b"[notice] A new release of pip is available: 23.0 -> 24.0\n"));
+}
@@ -1,4 +1,6 @@
-use stop_nagging::yaml_config::YamlConfig;
+use stop_nagging::yaml_config::{
+    ActionKind, CommandLine, EnvMode, EnvPolicy, EnvSeparator, YamlConfig,
+};
 
 #[test]
 fn test_parse_empty_yaml() {
@@ -33,7 +35,7 @@ ecosystems:
     let tool = &ecosystem.tools[0];
     assert_eq!(tool.name, "test-tool");
     assert_eq!(tool.executable, "test");
-    assert_eq!(tool.env.get("TEST_VAR").unwrap(), "test_value");
+    assert_eq!(tool.env.get("TEST_VAR").unwrap().value, "test_value");
     assert_eq!(tool.commands, vec!["test command"]);
     assert!(!tool.skip);
 }
@@ -65,3 +67,285 @@ fn test_from_default() {
     let config = YamlConfig::from_default().unwrap();
     assert!(!config.ecosystems.is_empty());
 }
+
+#[test]
+fn test_parse_env_policy() {
+    let yaml = r#"
+ecosystems:
+  test:
+    tools:
+      - name: test-tool
+        executable: test
+        env:
+          PLAIN_VAR: "1"
+          TELEMETRY_DISABLED:
+            value: "1"
+            policy: override-if-enabled
+"#;
+    let config: YamlConfig = serde_yaml::from_str(yaml).unwrap();
+    let tool = &config.ecosystems.get("test").unwrap().tools[0];
+    assert_eq!(tool.env["PLAIN_VAR"].policy, EnvPolicy::Keep);
+    assert_eq!(tool.env["TELEMETRY_DISABLED"].value, "1");
+    assert_eq!(
+        tool.env["TELEMETRY_DISABLED"].policy,
+        EnvPolicy::OverrideIfEnabled
+    );
+}
+
+#[test]
+fn test_parse_env_scalar_values() {
+    let yaml = r#"
+ecosystems:
+  test:
+    tools:
+      - name: test-tool
+        executable: test
+        env:
+          NUMBER: 1
+          FLAG: true
+          DETAILED:
+            value: 0
+            policy: override
+"#;
+    let config: YamlConfig = serde_yaml::from_str(yaml).unwrap();
+    let tool = &config.ecosystems.get("test").unwrap().tools[0];
+    assert_eq!(tool.env["NUMBER"].value, "1");
+    assert_eq!(tool.env["FLAG"].value, "true");
+    assert_eq!(tool.env["DETAILED"].value, "0");
+}
+
+#[test]
+fn test_parse_env_reports_unknown_fields_and_policies() {
+    let yaml = r#"
+ecosystems:
+  test:
+    tools:
+      - name: test-tool
+        executable: test
+        env:
+          TELEMETRY_DISABLED:
+            value: "1"
+            polcy: override
+"#;
+    let err = serde_yaml::from_str::<YamlConfig>(yaml).unwrap_err();
+    assert!(err.to_string().contains("unknown field `polcy`"), "{}", err);
+
+    let yaml = yaml.replace("polcy: override", "policy: overide");
+    let err = serde_yaml::from_str::<YamlConfig>(&yaml).unwrap_err();
+    assert!(
+        err.to_string().contains("unknown variant `overide`"),
+        "{}",
+        err
+    );
+}
+
+#[test]
+fn test_parse_env_append_token() {
+    let yaml = r#"
+ecosystems:
+  test:
+    tools:
+      - name: test-tool
+        executable: test
+        env:
+          TOOL_OPTS:
+            value: "-Dquiet=true"
+            mode: append_token
+          TOOL_PATH:
+            value: "/opt/quiet"
+            mode: append_token
+            separator: path
+"#;
+    let config: YamlConfig = serde_yaml::from_str(yaml).unwrap();
+    let tool = &config.ecosystems.get("test").unwrap().tools[0];
+    assert_eq!(tool.env["TOOL_OPTS"].mode, EnvMode::AppendToken);
+    assert_eq!(tool.env["TOOL_OPTS"].separator, EnvSeparator::Space);
+    assert_eq!(tool.env["TOOL_PATH"].separator, EnvSeparator::Path);
+}
+
+#[test]
+fn test_parse_timeouts() {
+    let yaml = r#"
+ecosystems:
+  test:
+    tools:
+      - name: test-tool
+        executable: test
+        timeout: 30
+        commands:
+          - "plain command"
+          - run: "slow command"
+            timeout: 2.5
+"#;
+    let config: YamlConfig = serde_yaml::from_str(yaml).unwrap();
+    let tool = &config.ecosystems.get("test").unwrap().tools[0];
+    assert_eq!(tool.timeout, Some(30.0));
+    assert_eq!(tool.commands[0].run, "plain command");
+    assert_eq!(tool.commands[0].timeout, None);
+    assert_eq!(tool.commands[1].run, "slow command");
+    assert_eq!(tool.commands[1].timeout, Some(2.5));
+}
+
+#[test]
+fn test_parse_argv_commands() {
+    let yaml = r#"
+ecosystems:
+  test:
+    tools:
+      - name: test-tool
+        executable: test
+        commands:
+          - ["npm", "config", "set", "update-notifier", "false"]
+          - run: ["echo", "it's here"]
+            timeout: 5
+            system: ["echo", "everyone"]
+"#;
+    let config: YamlConfig = serde_yaml::from_str(yaml).unwrap();
+    let tool = &config.ecosystems.get("test").unwrap().tools[0];
+    assert_eq!(
+        tool.commands[0].run,
+        CommandLine::Argv(
+            ["npm", "config", "set", "update-notifier", "false"]
+                .map(String::from)
+                .to_vec()
+        )
+    );
+    assert_eq!(tool.commands[1].timeout, Some(5.0));
+    assert_eq!(tool.commands[1].to_string(), r"echo 'it'\''s here'");
+    assert_eq!(tool.commands[0].system, None);
+    assert_eq!(
+        tool.commands[1].system,
+        Some(CommandLine::from(vec![
+            "echo".to_string(),
+            "everyone".to_string()
+        ]))
+    );
+}
+
+#[test]
+fn test_parse_command_reports_unknown_fields() {
+    let yaml = r#"
+ecosystems:
+  test:
+    tools:
+      - name: test-tool
+        executable: test
+        commands:
+          - run: "tool config set quiet true"
+            sytem: "tool config set --global quiet true"
+"#;
+    let err = serde_yaml::from_str::<YamlConfig>(yaml).unwrap_err();
+    assert!(err.to_string().contains("unknown field `sytem`"), "{}", err);
+}
+
+#[test]
+fn test_parse_interactive_and_non_interactive_env() {
+    let yaml = r#"
+non_interactive_env:
+  CI: "true"
+ecosystems:
+  test:
+    tools:
+      - name: test-tool
+        executable: test
+        interactive: true
+"#;
+    let config: YamlConfig = serde_yaml::from_str(yaml).unwrap();
+    assert!(config.ecosystems["test"].tools[0].interactive);
+    assert_eq!(config.non_interactive_env.len(), 1);
+    assert_eq!(config.non_interactive_env["CI"], "true");
+
+    let config: YamlConfig = serde_yaml::from_str("ecosystems: {}").unwrap();
+    assert_eq!(
+        config.non_interactive_env["DEBIAN_FRONTEND"],
+        "noninteractive"
+    );
+}
+
+#[test]
+fn test_parse_kinds() {
+    let yaml = r#"
+ecosystems:
+  test:
+    tools:
+      - name: test-tool
+        executable: test
+        kind: telemetry
+        env:
+          TEST_VAR: "1"
+          TEST_PROMPT:
+            value: "1"
+            kind: prompt
+        commands:
+          - "plain command"
+          - run: ["cache", "on"]
+            kind: behavior
+"#;
+    let config: YamlConfig = serde_yaml::from_str(yaml).unwrap();
+    let tool = &config.ecosystems.get("test").unwrap().tools[0];
+    assert_eq!(tool.env_kind("TEST_VAR"), Some(ActionKind::Telemetry));
+    assert_eq!(tool.env_kind("TEST_PROMPT"), Some(ActionKind::Prompt));
+    assert_eq!(tool.command_kind(0), Some(ActionKind::Telemetry));
+    assert_eq!(tool.command_kind(1), Some(ActionKind::Behavior));
+}
+
+#[test]
+fn test_every_builtin_action_has_a_kind() {
+    let config = YamlConfig::from_default().unwrap();
+    for ecosystem in config.ecosystems.values() {
+        for tool in &ecosystem.tools {
+            for key in tool.env.keys() {
+                assert!(
+                    tool.env_kind(key).is_some(),
+                    "{} has no kind",
+                    tool.env_action_id(key)
+                );
+            }
+            for index in 0..tool.commands.len() {
+                assert!(
+                    tool.command_kind(index).is_some(),
+                    "{} has no kind",
+                    tool.command_action_id(index)
+                );
+            }
+        }
+    }
+}
+
+/// Project commands run again with `--force` and in every workspace member,
+/// so running them twice must not add a setting twice.
+#[cfg(unix)]
+#[test]
+fn test_builtin_gradle_commands_are_idempotent() {
+    use std::time::Duration;
+    use stop_nagging::executor::{run_command_with, ExecOptions};
+    use stop_nagging::report::CommandOutcome;
+    use stop_nagging::resolved_env::ResolvedEnv;
+
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let properties = temp_dir.path().join("gradle.properties");
+    std::fs::write(&properties, "org.gradle.jvmargs=-Xmx2g\n").unwrap();
+    let config = YamlConfig::from_default().unwrap();
+    let gradle = config.ecosystems["java"]
+        .tools
+        .iter()
+        .find(|tool| tool.name == "gradle")
+        .unwrap();
+
+    let options = ExecOptions {
+        current_dir: Some(temp_dir.path().to_path_buf()),
+        ..ExecOptions::new(Duration::from_secs(5))
+    };
+    for _ in 0..2 {
+        for command in &gradle.commands {
+            let outcome = run_command_with(&command.run, &ResolvedEnv::default(), &options);
+            assert_eq!(outcome, CommandOutcome::Success);
+        }
+    }
+    assert_eq!(
+        std::fs::read_to_string(&properties).unwrap(),
+        "org.gradle.jvmargs=-Xmx2g\n\
+         org.gradle.caching=false\n\
+         org.gradle.logging.level=QUIET\n"
+    );
+}